@@ -16,6 +16,8 @@ pub struct Env {
     pub nodetrait_mut_sig: f64,
     // The power of a linkweight mutation
     pub weight_mut_power: f64,
+    // The power of a node dynamics mutation, on the time constant and bias of a node
+    pub dynamics_mut_power: f64,
     // Prob. that a link mutation which doesn't have to be recurrent will be made recurrent
     pub recur_prob: f64,
 
@@ -29,9 +31,24 @@ pub struct Env {
     pub disjoint_coeff: f64,
     pub excess_coeff: f64,
     pub mutdiff_coeff: f64,
+    // Importance of the difference between the traits with the same id
+    pub trait_diff_coeff: f64,
+    // Importance of the mean weight difference between matching genes
+    pub weight_diff_coeff: f64,
+    // Importance of the node genes that differ between the genomes
+    pub node_diff_coeff: f64,
+    // Genomes with at least this many genes have their disjoint, excess and node
+    // differences divided by their size, 0 never divides
+    pub compat_normalize_cutoff: usize,
 
     // This global tells compatibility threshold under which two Genomes are considered the same species
     pub compat_threshold: f64,
+    // Number of species the compatibility threshold is adjusted to reach every generation, 0 keeps it fixed
+    pub target_species: usize,
+    // Amount the compatibility threshold changes by in a generation
+    pub compat_threshold_step: f64,
+    // The compatibility threshold is never lowered below this
+    pub compat_threshold_min: f64,
 
     // Globals involved in the epoch cycle - mating, reproduction, etc..
     // How much does age matter?
@@ -48,6 +65,12 @@ pub struct Env {
     pub mutate_gene_reenable_prob: f64,
     pub mutate_add_node_prob: f64,
     pub mutate_add_link_prob: f64,
+    // Prob. of changing the activation function of a hidden node
+    pub mutate_function_type_prob: f64,
+    // Prob. of changing the aggregation function of a hidden node
+    pub mutate_aggregation_prob: f64,
+    // Prob. of perturbing the time constant and bias of a node (for CTRNNs)
+    pub mutate_node_dynamics_prob: f64,
     // Prob. of a mate being outside species
     pub interspecies_mate_rate: f64,
     pub mate_multipoint_prob: f64,
@@ -80,11 +103,19 @@ impl Env {
             linktrait_mut_sig: 0.0,
             nodetrait_mut_sig: 0.0,
             weight_mut_power: 0.0,
+            dynamics_mut_power: 0.0,
             recur_prob: 0.0,
             disjoint_coeff: 0.0,
             excess_coeff: 0.0,
             mutdiff_coeff: 0.0,
+            trait_diff_coeff: 0.0,
+            weight_diff_coeff: 0.0,
+            node_diff_coeff: 0.0,
+            compat_normalize_cutoff: 0,
             compat_threshold: 0.0,
+            target_species: 0,
+            compat_threshold_step: 0.0,
+            compat_threshold_min: 0.0,
             age_significance: 0.0,
             survival_thresh: 0.0,
             mutate_only_prob: 0.0,
@@ -96,6 +127,9 @@ impl Env {
             mutate_gene_reenable_prob: 0.0,
             mutate_add_node_prob: 0.0,
             mutate_add_link_prob: 0.0,
+            mutate_function_type_prob: 0.0,
+            mutate_aggregation_prob: 0.0,
+            mutate_node_dynamics_prob: 0.0,
             interspecies_mate_rate: 0.0,
             mate_multipoint_prob: 0.0,
             mate_multipoint_avg_prob: 0.0,
@@ -130,11 +164,19 @@ impl Env {
                         "linktrait_mut_sig" => env.linktrait_mut_sig = value.parse().map_err(|e| io_Error::new(io_ErrorKind::Other, format!("Error reading value linktrait_mut_sig: {}", e)))?,
                         "nodetrait_mut_sig" => env.nodetrait_mut_sig = value.parse().map_err(|e| io_Error::new(io_ErrorKind::Other, format!("Error reading value nodetrait_mut_sig: {}", e)))?,
                         "weight_mut_power" => env.weight_mut_power = value.parse().map_err(|e| io_Error::new(io_ErrorKind::Other, format!("Error reading value weight_mut_power: {}", e)))?,
+                        "dynamics_mut_power" => env.dynamics_mut_power = value.parse().map_err(|e| io_Error::new(io_ErrorKind::Other, format!("Error reading value dynamics_mut_power: {}", e)))?,
                         "recur_prob" => env.recur_prob = value.parse().map_err(|e| io_Error::new(io_ErrorKind::Other, format!("Error reading value recur_prob: {}", e)))?,
                         "disjoint_coeff" => env.disjoint_coeff = value.parse().map_err(|e| io_Error::new(io_ErrorKind::Other, format!("Error reading value disjoint_coeff: {}", e)))?,
                         "excess_coeff" => env.excess_coeff = value.parse().map_err(|e| io_Error::new(io_ErrorKind::Other, format!("Error reading value excess_coeff: {}", e)))?,
                         "mutdiff_coeff" => env.mutdiff_coeff = value.parse().map_err(|e| io_Error::new(io_ErrorKind::Other, format!("Error reading value mutdiff_coeff: {}", e)))?,
-                        "compat_threshold" => env.compat_threshold = value.parse().map_err(|e| io_Error::new(io_ErrorKind::Other, format!("Error reading value compat_threshold: {}", e)))?,
+                        "trait_diff_coeff" => env.trait_diff_coeff = value.parse().map_err(|e| io_Error::new(io_ErrorKind::Other, format!("Error reading value trait_diff_coeff: {}", e)))?,
+                        "weight_diff_coeff" => env.weight_diff_coeff = value.parse().map_err(|e| io_Error::new(io_ErrorKind::Other, format!("Error reading value weight_diff_coeff: {}", e)))?,
+                        "node_diff_coeff" => env.node_diff_coeff = value.parse().map_err(|e| io_Error::new(io_ErrorKind::Other, format!("Error reading value node_diff_coeff: {}", e)))?,
+                        "compat_normalize_cutoff" => env.compat_normalize_cutoff = value.parse().map_err(|e| io_Error::new(io_ErrorKind::Other, format!("Error reading value compat_normalize_cutoff: {}", e)))?,
+                        "compat_threshold" | "compat_thresh" => env.compat_threshold = value.parse().map_err(|e| io_Error::new(io_ErrorKind::Other, format!("Error reading value compat_threshold: {}", e)))?,
+                        "target_species" => env.target_species = value.parse().map_err(|e| io_Error::new(io_ErrorKind::Other, format!("Error reading value target_species: {}", e)))?,
+                        "compat_threshold_step" => env.compat_threshold_step = value.parse().map_err(|e| io_Error::new(io_ErrorKind::Other, format!("Error reading value compat_threshold_step: {}", e)))?,
+                        "compat_threshold_min" => env.compat_threshold_min = value.parse().map_err(|e| io_Error::new(io_ErrorKind::Other, format!("Error reading value compat_threshold_min: {}", e)))?,
                         "age_significance" => env.age_significance = value.parse().map_err(|e| io_Error::new(io_ErrorKind::Other, format!("Error reading value age_significance: {}", e)))?,
                         "survival_thresh" => env.survival_thresh = value.parse().map_err(|e| io_Error::new(io_ErrorKind::Other, format!("Error reading value survival_thresh: {}", e)))?,
                         "mutate_only_prob" => env.mutate_only_prob = value.parse().map_err(|e| io_Error::new(io_ErrorKind::Other, format!("Error reading value mutate_only_prob: {}", e)))?,
@@ -146,6 +188,9 @@ impl Env {
                         "mutate_gene_reenable_prob" => env.mutate_gene_reenable_prob = value.parse().map_err(|e| io_Error::new(io_ErrorKind::Other, format!("Error reading value mutate_gene_reenable_prob: {}", e)))?,
                         "mutate_add_node_prob" => env.mutate_add_node_prob = value.parse().map_err(|e| io_Error::new(io_ErrorKind::Other, format!("Error reading value mutate_add_node_prob: {}", e)))?,
                         "mutate_add_link_prob" => env.mutate_add_link_prob = value.parse().map_err(|e| io_Error::new(io_ErrorKind::Other, format!("Error reading value mutate_add_link_prob: {}", e)))?,
+                        "mutate_function_type_prob" => env.mutate_function_type_prob = value.parse().map_err(|e| io_Error::new(io_ErrorKind::Other, format!("Error reading value mutate_function_type_prob: {}", e)))?,
+                        "mutate_aggregation_prob" => env.mutate_aggregation_prob = value.parse().map_err(|e| io_Error::new(io_ErrorKind::Other, format!("Error reading value mutate_aggregation_prob: {}", e)))?,
+                        "mutate_node_dynamics_prob" => env.mutate_node_dynamics_prob = value.parse().map_err(|e| io_Error::new(io_ErrorKind::Other, format!("Error reading value mutate_node_dynamics_prob: {}", e)))?,
                         "interspecies_mate_rate" => env.interspecies_mate_rate = value.parse().map_err(|e| io_Error::new(io_ErrorKind::Other, format!("Error reading value interspecies_mate_rate: {}", e)))?,
                         "mate_multipoint_prob" => env.mate_multipoint_prob = value.parse().map_err(|e| io_Error::new(io_ErrorKind::Other, format!("Error reading value mate_multipoint_prob: {}", e)))?,
                         "mate_multipoint_avg_prob" => env.mate_multipoint_avg_prob = value.parse().map_err(|e| io_Error::new(io_ErrorKind::Other, format!("Error reading value mate_multipoint_avg_prob: {}", e)))?,
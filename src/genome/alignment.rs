@@ -0,0 +1,77 @@
+use super::Genome;
+use super::gene::Gene;
+
+/// ---------------------------------------------
+/// GENOME ALIGNMENT STRUCT:
+///   The genes of two genomes lined up by
+///   innovation number, as used by crossover
+///   and by the compatibility measure
+/// ---------------------------------------------
+#[derive(Debug, Clone)]
+pub struct GenomeAlignment<'a> {
+    /// All the genes in innovation order. Matching genes come together,
+    /// the others are paired with None on the side of the genome missing them
+    pub pairs: Vec<(Option<&'a Gene>, Option<&'a Gene>)>,
+    /// The pairs of genes with the same innovation number
+    pub matching: Vec<(&'a Gene, &'a Gene)>,
+    /// The genes of the first genome missing from the second one, within its innovation range
+    pub disjoint1: Vec<&'a Gene>,
+    /// The genes of the second genome missing from the first one, within its innovation range
+    pub disjoint2: Vec<&'a Gene>,
+    /// The genes of the first genome past the last innovation of the second one
+    pub excess1: Vec<&'a Gene>,
+    /// The genes of the second genome past the last innovation of the first one
+    pub excess2: Vec<&'a Gene>,
+}
+
+impl<'a> GenomeAlignment<'a> {
+    /// Aligns the genes of two genomes, which are kept in innovation order
+    pub fn new(genome1: &'a Genome, genome2: &'a Genome) -> Self
+    {
+        let mut alignment = GenomeAlignment {
+            pairs: Vec::with_capacity(genome1.genes.len().max(genome2.genes.len())),
+            matching: Vec::new(),
+            disjoint1: Vec::new(),
+            disjoint2: Vec::new(),
+            excess1: Vec::new(),
+            excess2: Vec::new(),
+        };
+
+        let mut genes1 = genome1.genes.iter().peekable();
+        let mut genes2 = genome2.genes.iter().peekable();
+
+        loop {
+            let pair = match (genes1.peek(), genes2.peek()) {
+                (Some(gene1), Some(gene2)) => {
+                    if gene1.get_innovation_num() < gene2.get_innovation_num() {
+                        alignment.disjoint1.push(*gene1);
+                        (genes1.next(), None)
+                    } else if gene1.get_innovation_num() > gene2.get_innovation_num() {
+                        alignment.disjoint2.push(*gene2);
+                        (None, genes2.next())
+                    } else {
+                        alignment.matching.push((*gene1, *gene2));
+                        (genes1.next(), genes2.next())
+                    }
+                }
+                //One genome has ended, the rest of the other one is excess
+                (Some(gene1), None) => {
+                    alignment.excess1.push(*gene1);
+                    (genes1.next(), None)
+                }
+                (None, Some(gene2)) => {
+                    alignment.excess2.push(*gene2);
+                    (None, genes2.next())
+                }
+                (None, None) => break,
+            };
+            alignment.pairs.push(pair);
+        }
+
+        alignment
+    }
+
+    pub fn matching_n(&self) -> usize { self.matching.len() }
+    pub fn disjoint_n(&self) -> usize { self.disjoint1.len() + self.disjoint2.len() }
+    pub fn excess_n(&self) -> usize { self.excess1.len() + self.excess2.len() }
+}
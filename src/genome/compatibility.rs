@@ -0,0 +1,112 @@
+use super::Genome;
+use env::Env;
+
+/// ---------------------------------------------
+/// COMPATIBILITY STRUCT:
+///   How the distance between two genomes is
+///   measured for speciation. Each term is
+///   weighted by its coefficient, a coefficient
+///   of 0 leaves the term out
+/// ---------------------------------------------
+#[derive(Debug, Clone, PartialEq)]
+pub struct Compatibility {
+    /// Importance of the disjoint genes
+    pub disjoint_coeff: f64,
+    /// Importance of the excess genes
+    pub excess_coeff: f64,
+    /// Importance of the mean mutation_num difference of the matching genes
+    pub mutdiff_coeff: f64,
+    /// Importance of the mean weight difference of the matching genes
+    pub weight_diff_coeff: f64,
+    /// Importance of the nodes only one genome has, or that have a different
+    /// function or aggregation in the two genomes
+    pub node_diff_coeff: f64,
+    /// Importance of the distance between the traits with the same id
+    pub trait_diff_coeff: f64,
+    /// When set, the disjoint, excess and node counts are divided by the number of
+    /// genes of the larger genome, unless it has fewer genes than this cutoff
+    /// (NEAT uses 20, so that small genomes are not normalized)
+    pub normalize_cutoff: Option<usize>,
+}
+
+impl Compatibility {
+    /// The compatibility measure configured in the Env
+    pub fn from_env(env: &Env) -> Self
+    {
+        Compatibility {
+            disjoint_coeff: env.disjoint_coeff,
+            excess_coeff: env.excess_coeff,
+            mutdiff_coeff: env.mutdiff_coeff,
+            weight_diff_coeff: env.weight_diff_coeff,
+            node_diff_coeff: env.node_diff_coeff,
+            trait_diff_coeff: env.trait_diff_coeff,
+            normalize_cutoff: if env.compat_normalize_cutoff > 0 { Some(env.compat_normalize_cutoff) } else { None },
+        }
+    }
+
+    /// The distance between two genomes
+    pub fn distance(&self, genome1: &Genome, genome2: &Genome) -> f64
+    {
+        let alignment = genome1.align(genome2);
+        let num_disjoint = alignment.disjoint_n() as f64;
+        let num_excess = alignment.excess_n() as f64;
+        let num_matching = alignment.matching_n() as f64;
+
+        let mut mut_diff_total = 0.0;
+        let mut weight_diff_total = 0.0;
+        for (gene1, gene2) in &alignment.matching {
+            mut_diff_total += (gene1.mutation_num - gene2.mutation_num).abs();
+            weight_diff_total += (gene1.link.weight - gene2.link.weight).abs();
+        }
+
+        //Get the length of the longest Genome for percentage computations
+        let max_genome_size = genome1.genes.len().max(genome2.genes.len());
+        let normalizer = match self.normalize_cutoff {
+            Some(cutoff) if max_genome_size >= cutoff && max_genome_size > 0 => max_genome_size as f64,
+            _ => 1.0,
+        };
+
+        //Without matching genes there is no parametric difference to average
+        let (mut_diff, weight_diff) = if num_matching > 0.0 {
+            (mut_diff_total / num_matching, weight_diff_total / num_matching)
+        } else {
+            (0.0, 0.0)
+        };
+
+        let mut distance = self.disjoint_coeff * (num_disjoint / normalizer)
+            + self.excess_coeff * (num_excess / normalizer)
+            + self.mutdiff_coeff * mut_diff
+            + self.weight_diff_coeff * weight_diff;
+
+        if self.node_diff_coeff != 0.0 {
+            distance += self.node_diff_coeff * (Compatibility::node_differences(genome1, genome2) / normalizer);
+        }
+        if self.trait_diff_coeff != 0.0 {
+            distance += self.trait_diff_coeff * genome1.trait_distance(genome2);
+        }
+
+        distance
+    }
+
+    /// Counts the nodes only one genome has, and the ones with a different
+    /// function or aggregation in the two genomes
+    fn node_differences(genome1: &Genome, genome2: &Genome) -> f64
+    {
+        let mut differences = 0.0;
+
+        for node1 in &genome1.nodes {
+            match genome2.get_node(node1.id) {
+                Some(node2) => {
+                    if node1.function_type() != node2.function_type() || node1.aggregation() != node2.aggregation() {
+                        differences += 1.0;
+                    }
+                }
+                None => differences += 1.0,
+            }
+        }
+
+        differences + genome2.nodes.iter()
+            .filter(|node2| genome1.get_node(node2.id).is_none())
+            .count() as f64
+    }
+}
@@ -1,8 +1,4 @@
-use std::rc::Rc;
-use std::cell::RefCell;
 use link::Link;
-use genome::gene_trait::Trait;
-use node::Node;
 
 #[derive(Clone, Debug, PartialEq)]
 pub struct Gene {
@@ -20,7 +16,7 @@ pub struct Gene {
 }
 
 impl Gene {
-    pub fn new(trait_gene: Option<Rc<RefCell<Trait>>>, i_node: Rc<RefCell<Node>>, o_node: Rc<RefCell<Node>>, weight: f64, recurrent: bool, innovation_num: f64, mutation_num: f64, enable: bool) -> Self
+    pub fn new(trait_gene: Option<i32>, i_node: i32, o_node: i32, weight: f64, recurrent: bool, innovation_num: f64, mutation_num: f64, enable: bool) -> Self
     {
         let link = Link::new(trait_gene, weight, i_node, o_node, recurrent);
 
@@ -39,4 +35,5 @@ impl Gene {
     pub fn get_innovation_num(&self) -> f64 { self.innovation_num }
 
     pub fn enabled(&self) -> bool { self.enable }
+    pub fn set_enable(&mut self, enable: bool) { self.enable = enable }
 }
\ No newline at end of file
@@ -1,3 +1,5 @@
+use rand;
+
 pub const NUM_TRAIT_PARAMS: usize = 8;
 
 #[derive(Debug, Clone, PartialEq)]
@@ -10,4 +12,41 @@ impl Trait {
     pub fn new(id: i32, params: [f64; NUM_TRAIT_PARAMS]) -> Self {
         Trait { id, params }
     }
+
+    /// Creates a trait averaging the parameters of two traits, with the id of the first one
+    pub fn blend(first: &Trait, second: &Trait) -> Self {
+        let mut params = [0.0; NUM_TRAIT_PARAMS];
+        for (param, (first_param, second_param)) in params.iter_mut().zip(first.params.iter().zip(&second.params)) {
+            *param = (first_param + second_param) / 2.0;
+        }
+
+        Trait { id: first.id, params }
+    }
+
+    pub fn params(&self) -> &[f64; NUM_TRAIT_PARAMS] { &self.params }
+
+    /// The mean absolute difference between the parameters of two traits
+    pub fn distance(&self, other: &Trait) -> f64
+    {
+        let total: f64 = self.params.iter()
+            .zip(&other.params)
+            .map(|(param, other_param)| (param - other_param).abs())
+            .sum();
+
+        total / NUM_TRAIT_PARAMS as f64
+    }
+
+    /// Perturbs each parameter with probability prob by a random amount up to power,
+    /// keeping the parameters between 0 and 1
+    pub fn mutate(&mut self, power: f64, prob: f64)
+    {
+        for param in &mut self.params {
+            if rand::random::<f64>() < prob {
+                let random_num = (if rand::random::<bool>() { 1.0 } else { -1.0 })
+                    * rand::random::<f64>()
+                    * power;
+                *param = (*param + random_num).clamp(0.0, 1.0);
+            }
+        }
+    }
 }
\ No newline at end of file
@@ -1,15 +1,21 @@
 pub mod gene;
 pub mod gene_trait;
+pub mod compatibility;
+pub mod alignment;
 
 use self::gene_trait::Trait;
 use self::gene::Gene;
+use self::compatibility::Compatibility;
+use self::alignment::GenomeAlignment;
 use Mutator;
 use env::Env;
 use rand;
+use rand::Rng;
 use rand::distributions::{IndependentSample, Range};
-use node::Node;
-use std::rc::Rc;
-use std::cell::RefCell;
+use node::{Node, NodeType, NodePlace, FunctionType, AggregationType};
+use link::Link;
+use network::Network;
+use innovation::{Innovation, InnovationTable};
 use std::collections::HashMap;
 use std::path::Path;
 use std::fs::File;
@@ -19,8 +25,8 @@ use std::io::Result as ioResult;
 #[derive(Debug)]
 pub struct Genome {
     pub id: i32,
-    traits: Vec<Rc<RefCell<Trait>>>,
-    nodes: Vec<Rc<RefCell<Node>>>,
+    traits: Vec<Trait>,
+    nodes: Vec<Node>,
     genes: Vec<Gene>,
 }
 
@@ -36,6 +42,13 @@ impl Genome {
     }
 
     pub fn nodes_n(&self) -> usize { self.nodes.len() }
+    pub fn traits(&self) -> &[Trait] { &self.traits }
+    pub fn genes(&self) -> &[Gene] { &self.genes }
+
+    pub fn get_node(&self, id: i32) -> Option<&Node>
+    {
+        self.nodes.iter().find(|node| node.id == id)
+    }
 
     pub fn extrons(&self) -> usize
     {
@@ -47,12 +60,12 @@ impl Genome {
         total
     }
 
-    pub fn add_trait(&mut self, gene_trait: Rc<RefCell<Trait>>)
+    pub fn add_trait(&mut self, gene_trait: Trait)
     {
         self.traits.push(gene_trait)
     }
 
-    pub fn add_node(&mut self, node: Rc<RefCell<Node>>)
+    pub fn add_node(&mut self, node: Node)
     {
         self.nodes.push(node)
     }
@@ -63,8 +76,8 @@ impl Genome {
     }
 
     pub fn clone(&self, new_id: i32) -> Self {
-        let mut traits_hash = HashMap::new();
-        let mut nodes_hash = HashMap::new();
+        //Nodes and links without a trait get the first one
+        let default_trait = self.traits.first().map(|first_trait| first_trait.id);
 
         let mut new_genome = Genome {
             id: new_id,
@@ -73,40 +86,58 @@ impl Genome {
             genes: Vec::new(),
         };
 
-        for new_trait_ref in &self.traits {
-            let new_trait = new_trait_ref.clone();
-            let id = new_trait.borrow().id;
-            traits_hash.insert(id, new_trait);
-        }
-
         for node in &self.nodes {
-            let mut new_node = node.borrow().duplicate();
-            if let Some(old_nodetrait) = new_node.node_trait {
-                new_node.node_trait = Some(traits_hash.get(&old_nodetrait.borrow().id).unwrap().clone())
-            } else {
-                new_node.node_trait = Some(new_genome.traits[0].clone())
+            let mut new_node = node.duplicate();
+            if new_node.node_trait.is_none() {
+                new_node.node_trait = default_trait;
             }
-            let new_node_arc = Rc::new(RefCell::new(new_node));
-            nodes_hash.insert(new_node_arc.borrow().id, new_node_arc.clone());
-            new_genome.nodes.push(new_node_arc);
+            new_genome.nodes.push(new_node);
         }
 
         for gene in &self.genes {
             let mut new_gene = gene.clone();
+            if new_gene.link.link_trait.is_none() {
+                new_gene.link.link_trait = default_trait;
+            }
+            new_genome.genes.push(new_gene);
+        }
 
-            new_gene.link.i_node = nodes_hash.get(&gene.link.i_node.borrow().id).unwrap().clone();
-            new_gene.link.o_node = nodes_hash.get(&gene.link.o_node.borrow().id).unwrap().clone();
+        new_genome
+    }
 
-            if let Some(ref link_trait_old) = gene.link.link_trait {
-                new_gene.link.link_trait = Some(traits_hash.get(&link_trait_old.borrow().id).unwrap().clone());
-            } else {
-                new_gene.link.link_trait = Some(new_genome.traits[0].clone());
+    /// Generate a Network phenotype from this Genome with the specified id.
+    /// Only enabled genes are expressed as links.
+    pub fn genesis(&self, id: i32) -> Network
+    {
+        let mut positions = HashMap::new();
+        let mut network = Network::new(id);
+
+        //Create the nodes, without any link attached
+        for node in &self.nodes {
+            let mut new_node = node.duplicate();
+            new_node.derive_trait(&self.traits);
+            positions.insert(node.id, network.add_node(new_node));
+        }
+
+        //Create the links by iterating through the genes
+        for gene in &self.genes {
+            //Only create the link if the gene is enabled
+            if !gene.enabled() {
+                continue;
             }
 
-            new_genome.genes.push(new_gene);
+            let mut new_link = Link::new(gene.link.link_trait,
+                                         gene.link.weight,
+                                         gene.link.i_node,
+                                         gene.link.o_node,
+                                         gene.link.is_recurrent());
+            new_link.set_time_delay(gene.link.is_time_delayed());
+            new_link.derive_trait(&self.traits);
+
+            network.add_link(new_link, positions[&gene.link.i_node], positions[&gene.link.o_node]);
         }
 
-        new_genome
+        network
     }
 
     pub fn mutate_link_weights(&mut self, power: f64, rate: f64, mutator_type: Mutator)
@@ -190,115 +221,747 @@ impl Genome {
         }
     }
 
-    pub fn randomize_traits(&mut self) {
-        let num_traits = self.traits.len();
-        let between: Range<usize> = Range::new(0, num_traits);
-        let mut rng = rand::thread_rng();
+    /// Picks a random hidden node that is not frozen
+    fn random_hidden_node(&mut self) -> Option<&mut Node>
+    {
+        let mut hidden_nodes: Vec<_> = self.nodes.iter_mut()
+            .filter(|node| node.node_place() == NodePlace::Hidden && !node.is_frozen())
+            .collect();
 
-        for node in &mut self.nodes {
-            let trait_num = between.ind_sample(&mut rng);
-            node.borrow_mut().node_trait = Some(self.traits[trait_num].clone());
+        if hidden_nodes.is_empty() {
+            return None;
         }
 
-        for gene in &mut self.genes {
-            let trait_num = between.ind_sample(&mut rng);
-            gene.link.link_trait = Some(self.traits[trait_num].clone());
+        let choice = rand::thread_rng().gen_range(0, hidden_nodes.len());
+        Some(hidden_nodes.swap_remove(choice))
+    }
+
+    /// Changes the activation function of a random hidden node to a different one.
+    /// Returns false if there is no hidden node that can be mutated
+    pub fn mutate_function_type(&mut self) -> bool
+    {
+        let node = match self.random_hidden_node() {
+            Some(node) => node,
+            None => return false,
+        };
+
+        let current = node.function_type();
+        let choices: Vec<_> = FunctionType::ALL.iter()
+            .filter(|function_type| **function_type != current)
+            .collect();
+        node.set_function_type(**rand::thread_rng().choose(&choices).unwrap());
+
+        true
+    }
+
+    /// Changes the aggregation function of a random hidden node to a different one.
+    /// Returns false if there is no hidden node that can be mutated
+    pub fn mutate_aggregation(&mut self) -> bool
+    {
+        let node = match self.random_hidden_node() {
+            Some(node) => node,
+            None => return false,
+        };
+
+        let current = node.aggregation();
+        let choices: Vec<_> = AggregationType::ALL.iter()
+            .filter(|aggregation| **aggregation != current)
+            .collect();
+        node.set_aggregation(**rand::thread_rng().choose(&choices).unwrap());
+
+        true
+    }
+
+    /// Perturbs the time constant and the bias of a random neuron, used by
+    /// continuous time networks. Returns false if there is no neuron that can be mutated
+    pub fn mutate_node_dynamics(&mut self, power: f64) -> bool
+    {
+        let mut neurons: Vec<_> = self.nodes.iter_mut()
+            .filter(|node| !node.is_sensor() && !node.is_frozen())
+            .collect();
+
+        let mut rng = rand::thread_rng();
+        let node = match rng.choose_mut(&mut neurons) {
+            Some(node) => node,
+            None => return false,
+        };
+
+        let time_constant = node.get_time_constant() + (rng.gen::<f64>() * 2.0 - 1.0) * power;
+        node.set_time_constant(time_constant);
+        let bias = node.get_bias() + (rng.gen::<f64>() * 2.0 - 1.0) * power;
+        node.set_bias(bias);
+
+        true
+    }
+
+    /// Mutates the parameters of a random trait. Returns false if there are no traits
+    pub fn mutate_random_trait(&mut self, power: f64, prob: f64) -> bool
+    {
+        if self.traits.is_empty() {
+            return false;
         }
+
+        let trait_num = rand::thread_rng().gen_range(0, self.traits.len());
+        self.traits[trait_num].mutate(power, prob);
+
+        true
     }
 
-    pub fn get_last_node_id(&self) -> Option<i32>
+    /// Points random genes to random traits, the given number of times.
+    /// The mutation_num of a changed gene is moved by up to mut_sig.
+    /// Frozen genes are left alone. Returns true if any gene changed
+    pub fn mutate_link_trait(&mut self, times: usize, mut_sig: f64) -> bool
     {
-        self.nodes.last().map(|node| node.borrow().id)
+        if self.traits.is_empty() || self.genes.is_empty() {
+            return false;
+        }
+
+        let mut rng = rand::thread_rng();
+        let mut changed = false;
+
+        for _ in 0..times {
+            let trait_id = self.traits[rng.gen_range(0, self.traits.len())].id;
+            let gene_num = rng.gen_range(0, self.genes.len());
+            let gene = &mut self.genes[gene_num];
+
+            if !gene.is_frozen() {
+                gene.link.link_trait = Some(trait_id);
+                gene.mutation_num += (if rng.gen::<bool>() { 1.0 } else { -1.0 })
+                    * rng.gen::<f64>()
+                    * mut_sig;
+                changed = true;
+            }
+        }
+
+        changed
     }
 
-    pub fn get_last_gene_innovnum(&self) -> Option<f64>
+    /// Points random nodes to random traits, the given number of times.
+    /// The mutation_num of the genes connected to a changed node is moved by up to mut_sig.
+    /// Frozen nodes are left alone. Returns true if any node changed
+    pub fn mutate_node_trait(&mut self, times: usize, mut_sig: f64) -> bool
     {
-        self.genes.last().map(|gene| gene.get_innovation_num())
+        if self.traits.is_empty() || self.nodes.is_empty() {
+            return false;
+        }
+
+        let mut rng = rand::thread_rng();
+        let mut changed = false;
+
+        for _ in 0..times {
+            let trait_id = self.traits[rng.gen_range(0, self.traits.len())].id;
+            let node_num = rng.gen_range(0, self.nodes.len());
+            let node = &mut self.nodes[node_num];
+
+            if node.is_frozen() {
+                continue;
+            }
+            node.node_trait = Some(trait_id);
+            let node_id = node.id;
+
+            for gene in &mut self.genes {
+                if !gene.is_frozen() && (gene.link.i_node == node_id || gene.link.o_node == node_id) {
+                    gene.mutation_num += (if rng.gen::<bool>() { 1.0 } else { -1.0 })
+                        * rng.gen::<f64>()
+                        * mut_sig;
+                }
+            }
+            changed = true;
+        }
+
+        changed
     }
 
-    pub fn compatibility(&self, other: &Genome, env: &Env) -> f64
+    /// Toggles the enable flag of random genes, the given number of times.
+    /// A gene is disabled only if its input node keeps another enabled
+    /// outgoing link, so that no node gets isolated from the network.
+    /// Returns true if any gene changed
+    pub fn mutate_toggle_enable(&mut self, times: usize) -> bool
     {
-        //Set up the counters
-        let mut num_disjoint = 0.0;
-        let mut num_excess = 0.0;
-        let mut mut_diff_total = 0.0;
-        let mut num_matching = 0.0;  //Used to normalize mutation_num differences
+        if self.genes.is_empty() {
+            return false;
+        }
 
-        //Get the length of the longest Genome for percentage computations
-        let max_genome_size =
-            if self.genes.len() > other.genes.len() {
-                self.genes.len()
+        let mut rng = rand::thread_rng();
+        let mut changed = false;
+
+        for _ in 0..times {
+            let position = rng.gen_range(0, self.genes.len());
+
+            if self.genes[position].enabled() {
+                //We need to make sure that another gene connects out of the in-node
+                //Because if not a section of network will break off and become isolated
+                let i_node = self.genes[position].link.i_node;
+                let safe = self.genes.iter().enumerate().any(|(other, gene)| {
+                    other != position && gene.enabled() && gene.link.i_node == i_node
+                });
+
+                if safe {
+                    self.genes[position].set_enable(false);
+                    changed = true;
+                }
             } else {
-                other.genes.len()
-            } as f64;
+                self.genes[position].set_enable(true);
+                changed = true;
+            }
+        }
 
-        //Now move through the Genes of each potential parent
-        //until both Genomes end
+        changed
+    }
 
-        let mut gene1_iter = self.genes.iter();
-        let mut gene2_iter = other.genes.iter();
+    /// Enables the first disabled gene. Returns false if all genes are enabled
+    pub fn mutate_gene_reenable(&mut self) -> bool
+    {
+        match self.genes.iter_mut().find(|gene| !gene.enabled()) {
+            Some(gene) => {
+                gene.set_enable(true);
+                true
+            }
+            None => false,
+        }
+    }
+
+    /// Inserts a node keeping the nodes ordered by id
+    fn insert_node(&mut self, node: Node)
+    {
+        let position = self.nodes.iter()
+            .position(|other| other.id > node.id)
+            .unwrap_or(self.nodes.len());
+        self.nodes.insert(position, node);
+    }
+
+    /// Inserts a gene keeping the genes ordered by innovation number
+    fn insert_gene(&mut self, gene: Gene)
+    {
+        let position = self.genes.iter()
+            .position(|other| other.get_innovation_num() > gene.get_innovation_num())
+            .unwrap_or(self.genes.len());
+        self.genes.insert(position, gene);
+    }
 
-        let mut p1gene = gene1_iter.next();
-        let mut p2gene = gene2_iter.next();
+    /// Picks an enabled gene that does not come out of a bias node to be split.
+    /// Small genomes are biased towards older genes, to avoid a chaining effect
+    /// that would split the newest links over and over
+    fn pick_gene_to_split(&self) -> Option<usize>
+    {
+        let splittable = |gene: &Gene| {
+            gene.enabled() && self.get_node(gene.link.i_node)
+                .is_some_and(|node| node.node_place() != NodePlace::Bias)
+        };
+        let mut rng = rand::thread_rng();
+
+        if self.genes.len() < 15 {
+            //Walk the genes from the oldest, stopping on each one with probability 0.3
+            for (position, gene) in self.genes.iter().enumerate() {
+                if splittable(gene) && rng.gen::<f64>() >= 0.7 {
+                    return Some(position);
+                }
+            }
+            //Fall back to the first splittable gene
+            self.genes.iter().position(splittable)
+        } else {
+            //Alternative uniform random choice of genes
+            //When the genome is not tiny, it is safe to choose randomly
+            (0..20)
+                .map(|_| rng.gen_range(0, self.genes.len()))
+                .find(|&position| splittable(&self.genes[position]))
+        }
+    }
 
-        while p1gene.is_some() || p2gene.is_some() {
-            if p1gene.is_none() {
-                p2gene = gene2_iter.next();
-                num_excess += 1.0;
-            } else if p2gene.is_none() {
-                p1gene = gene1_iter.next();
-                num_excess += 1.0;
+    /// Mutates the genome by splitting a random enabled gene with a new hidden node.
+    /// The old gene is disabled and replaced by in->new with weight 1.0 and
+    /// new->out with the old weight. If the same gene was already split in this
+    /// generation, the node id and innovation numbers are taken from the innovations,
+    /// otherwise cur_node_id and cur_innov_num, the last node id and innovation
+    /// number used in the population, are advanced.
+    /// Returns false if no gene could be split
+    pub fn mutate_add_node(&mut self, innovations: &mut InnovationTable, cur_node_id: &mut i32, cur_innov_num: &mut f64) -> bool
+    {
+        let position = match self.pick_gene_to_split() {
+            Some(position) => position,
+            None => return false,
+        };
+
+        //Disable the gene
+        self.genes[position].set_enable(false);
+
+        let old_link = self.genes[position].link.clone();
+        let old_innov_num = self.genes[position].get_innovation_num();
+
+        //Check whether this gene was already split in this generation.
+        //The recorded node cannot be reused if the genome already has it
+        let previous = innovations.find_new_node(old_link.i_node, old_link.o_node, old_innov_num)
+            .filter(|innovation| self.get_node(innovation.new_node_id).is_none())
+            .map(|innovation| (innovation.new_node_id, innovation.innovation_num1, innovation.innovation_num2));
+
+        let (new_node_id, innovation_num1, innovation_num2) = match previous {
+            Some(numbers) => numbers,
+            None => {
+                *cur_node_id += 1;
+                *cur_innov_num += 2.0;
+                let innovation = Innovation::new_node(old_link.i_node, old_link.o_node, *cur_innov_num - 1.0,
+                                                      *cur_innov_num, *cur_node_id, old_innov_num);
+                innovations.add(innovation);
+                (*cur_node_id, *cur_innov_num - 1.0, *cur_innov_num)
+            }
+        };
+
+        //Create the new node, with the first trait
+        let node_trait = self.traits.first().map(|first_trait| first_trait.id);
+        let new_node = Node::new(new_node_id, node_trait, NodeType::Neuron, NodePlace::Hidden);
+
+        //The new links keep the trait of the old one. The first one carries the
+        //old recurrency, the second one the old weight
+        let new_gene_1 = Gene::new(old_link.link_trait, old_link.i_node, new_node_id, 1.0,
+                                   old_link.is_recurrent(), innovation_num1, 0.0, true);
+        let new_gene_2 = Gene::new(old_link.link_trait, new_node_id, old_link.o_node, old_link.weight,
+                                   false, innovation_num2, 0.0, true);
+
+        self.insert_gene(new_gene_1);
+        self.insert_gene(new_gene_2);
+        self.insert_node(new_node);
+
+        true
+    }
+
+    /// Tells if a link from i_node to o_node would close a loop,
+    /// that is if i_node can already be reached from o_node through the enabled genes
+    pub fn is_recurrent_link(&self, i_node: i32, o_node: i32) -> bool
+    {
+        let mut visited = vec![o_node];
+        let mut to_visit = vec![o_node];
+
+        while let Some(node) = to_visit.pop() {
+            if node == i_node {
+                return true;
+            }
+            for gene in self.genes.iter().filter(|gene| gene.enabled() && gene.link.i_node == node) {
+                if !visited.contains(&gene.link.o_node) {
+                    visited.push(gene.link.o_node);
+                    to_visit.push(gene.link.o_node);
+                }
+            }
+        }
+
+        false
+    }
+
+    /// Mutates the genome by connecting two unconnected nodes with a new random link.
+    /// With probability recur_only_prob only recurrent links are searched, otherwise
+    /// recurrent links are allowed with probability recur_prob. Gives up after
+    /// newlink_tries attempts. If the same link was already made in this generation,
+    /// its innovation number and weight are taken from the innovations, otherwise
+    /// cur_innov_num, the last innovation number used in the population, is advanced.
+    /// Returns false if no link was added
+    pub fn mutate_add_link(&mut self, env: &Env, innovations: &mut InnovationTable, cur_innov_num: &mut f64) -> bool
+    {
+        let mut rng = rand::thread_rng();
+
+        //Decide whether to make this recurrent
+        let recur_only = rng.gen::<f64>() < env.recur_only_prob;
+        let recur_allowed = recur_only || rng.gen::<f64>() < env.recur_prob;
+
+        //Links can come out of any node but can only go into neurons
+        let neurons: Vec<i32> = self.nodes.iter()
+            .filter(|node| !node.is_sensor())
+            .map(|node| node.id)
+            .collect();
+        if neurons.is_empty() {
+            return false;
+        }
+
+        let mut found = None;
+        for _ in 0..env.newlink_tries {
+            //Loop recurrences are tried half of the time when looking for recurrent links
+            let (i_node, o_node) = if recur_only && rng.gen::<bool>() {
+                let node = neurons[rng.gen_range(0, neurons.len())];
+                (node, node)
             } else {
-                let p1gene_unwrapped = p1gene.unwrap();
-                let p2gene_unwrapped = p2gene.unwrap();
+                let i_node = self.nodes[rng.gen_range(0, self.nodes.len())].id;
+                (i_node, neurons[rng.gen_range(0, neurons.len())])
+            };
+
+            //Skip the pair if the link already exists
+            if self.genes.iter().any(|gene| gene.link.i_node == i_node && gene.link.o_node == o_node) {
+                continue;
+            }
+
+            let recurrent = self.is_recurrent_link(i_node, o_node);
+            if (recur_only && !recurrent) || (!recur_allowed && recurrent) {
+                continue;
+            }
+
+            found = Some((i_node, o_node, recurrent));
+            break;
+        }
+
+        let (i_node, o_node, recurrent) = match found {
+            Some(link) => link,
+            None => return false,
+        };
+
+        //Choose a random trait and a random weight for the new link
+        let link_trait = if self.traits.is_empty() {
+            None
+        } else {
+            Some(self.traits[rng.gen_range(0, self.traits.len())].id)
+        };
+
+        let previous = innovations.find_new_link(i_node, o_node, recurrent)
+            .map(|innovation| (innovation.innovation_num1, innovation.new_weight));
+
+        let (innovation_num, new_weight) = match previous {
+            Some(numbers) => numbers,
+            None => {
+                let new_weight = (if rng.gen::<bool>() { 1.0 } else { -1.0 }) * rng.gen::<f64>();
+                *cur_innov_num += 1.0;
+                innovations.add(Innovation::new_link(i_node, o_node, *cur_innov_num, new_weight, recurrent));
+                (*cur_innov_num, new_weight)
+            }
+        };
+
+        self.insert_gene(Gene::new(link_trait, i_node, o_node, new_weight, recurrent,
+                                   innovation_num, new_weight, true));
+
+        true
+    }
+
+    /// Tells which parent is fitter, the first one or the other one.
+    /// Between equally fit parents the smaller genome is considered fitter
+    fn first_is_fitter(&self, other: &Genome, fitness1: f64, fitness2: f64) -> bool
+    {
+        if fitness1 > fitness2 {
+            true
+        } else if fitness1 < fitness2 {
+            false
+        } else {
+            self.genes.len() < other.genes.len()
+        }
+    }
+
+    /// Returns the given trait id if the genome has it, otherwise its first trait
+    fn known_trait(&self, trait_id: Option<i32>) -> Option<i32>
+    {
+        match trait_id {
+            Some(id) if self.traits.iter().any(|gene_trait| gene_trait.id == id) => trait_id,
+            _ => self.traits.first().map(|first_trait| first_trait.id),
+        }
+    }
+
+    /// Adds a copy of a parent's node, unless a node with the same id is already present
+    fn inherit_node(&mut self, node: &Node)
+    {
+        if self.get_node(node.id).is_some() {
+            return;
+        }
+
+        let mut new_node = node.duplicate();
+        new_node.node_trait = self.known_trait(node.node_trait);
+        self.insert_node(new_node);
+    }
+
+    /// Adds a copy of a parent's gene together with its nodes, unless a gene
+    /// connecting the same nodes in the same way is already present
+    fn inherit_gene(&mut self, gene: &Gene, parent: &Genome)
+    {
+        let duplicate = self.genes.iter().any(|other| {
+            other.link.i_node == gene.link.i_node
+                && other.link.o_node == gene.link.o_node
+                && other.link.is_recurrent() == gene.link.is_recurrent()
+        });
+        if duplicate {
+            return;
+        }
+
+        for &node_id in &[gene.link.i_node, gene.link.o_node] {
+            if let Some(node) = parent.get_node(node_id) {
+                self.inherit_node(node);
+            }
+        }
+
+        let mut new_gene = gene.clone();
+        new_gene.link.link_trait = self.known_trait(gene.link.link_trait);
+        self.insert_gene(new_gene);
+    }
+
+    /// Lines up the genes of this genome with the ones of another genome by innovation number
+    pub fn align<'a>(&'a self, other: &'a Genome) -> GenomeAlignment<'a>
+    {
+        GenomeAlignment::new(self, other)
+    }
+
+    /// Blends the traits of two parents with the same id,
+    /// keeping the ones only the first parent has
+    fn blend_traits(first: &Genome, second: &Genome) -> Vec<Trait>
+    {
+        first.traits.iter()
+            .map(|first_trait| match second.traits.iter().find(|other| other.id == first_trait.id) {
+                Some(second_trait) => Trait::blend(first_trait, second_trait),
+                None => first_trait.clone(),
+            })
+            .collect()
+    }
+
+    /// Starts the offspring of two parents, with the given traits
+    /// and all the sensors and outputs of the fitter parent
+    fn offspring(fitter: &Genome, traits: Vec<Trait>, new_id: i32) -> Genome
+    {
+        let mut child = Genome {
+            id: new_id,
+            traits,
+            nodes: Vec::new(),
+            genes: Vec::new(),
+        };
 
+        for node in fitter.nodes.iter().filter(|node| node.node_place() != NodePlace::Hidden) {
+            child.inherit_node(node);
+        }
 
-                let p1innov = p1gene_unwrapped.get_innovation_num();
-                let p2innov = p2gene_unwrapped.get_innovation_num();
+        child
+    }
+
+    /// Tells if a gene inherited from two matching genes should be disabled:
+    /// when either of them is disabled, it is with probability 0.75
+    fn inherit_disabled(gene1: &Gene, gene2: &Gene) -> bool
+    {
+        (!gene1.enabled() || !gene2.enabled()) && rand::random::<f64>() < 0.75
+    }
+
+    /// Averages two matching genes: weight and mutation_num are averaged,
+    /// the trait is taken randomly from either of them
+    fn average_gene(gene1: &Gene, gene2: &Gene) -> Gene
+    {
+        let mut new_gene = gene1.clone();
+        new_gene.link.weight = (gene1.link.weight + gene2.link.weight) / 2.0;
+        new_gene.mutation_num = (gene1.mutation_num + gene2.mutation_num) / 2.0;
+        if rand::random::<bool>() {
+            new_gene.link.link_trait = gene2.link.link_trait;
+        }
+        new_gene.set_enable(!Genome::inherit_disabled(gene1, gene2));
 
-                if p1innov == p2innov {
-                    let mut mut_diff = p1gene_unwrapped.mutation_num - p2gene_unwrapped.mutation_num;
-                    if mut_diff < 0.0 {
-                        mut_diff = 0.0 - mut_diff;
+        new_gene
+    }
+
+    /// Mates this genome with another one, aligning their genes by innovation number.
+    /// Matching genes are inherited randomly from either parent, disjoint and excess
+    /// genes only from the fitter parent. When either matching gene is disabled,
+    /// the inherited one is disabled with probability 0.75. The traits of the
+    /// parents are blended
+    pub fn mate_multipoint(&self, other: &Genome, fitness1: f64, fitness2: f64, new_id: i32) -> Genome
+    {
+        let (fitter, weaker) = if self.first_is_fitter(other, fitness1, fitness2) {
+            (self, other)
+        } else {
+            (other, self)
+        };
+
+        let mut child = Genome::offspring(fitter, Genome::blend_traits(fitter, weaker), new_id);
+
+        for pair in fitter.align(weaker).pairs {
+            match pair {
+                (Some(gene1), Some(gene2)) => {
+                    let (chosen, parent) = if rand::random::<bool>() { (gene1, fitter) } else { (gene2, weaker) };
+                    let mut new_gene = chosen.clone();
+                    if Genome::inherit_disabled(gene1, gene2) {
+                        new_gene.set_enable(false);
                     }
-                    mut_diff_total += mut_diff;
-
-                    p1gene = gene1_iter.next();
-                    p2gene = gene2_iter.next();
-                    num_matching += 1.0;
-                } else if p1innov < p2innov {
-                    p1gene = gene1_iter.next();
-                    num_disjoint += 1.0;
-                } else if p2innov < p1innov {
-                    p2gene = gene2_iter.next();
-                    num_disjoint += 1.0;
+                    child.inherit_gene(&new_gene, parent);
                 }
+                //Disjoint and excess genes of the fitter parent
+                (Some(gene1), None) => child.inherit_gene(gene1, fitter),
+                _ => {}
             }
         }
 
-        env.disjoint_coeff * (num_disjoint / 1.0)
-            + env.excess_coeff * (num_excess / 1.0)
-            + env.mutdiff_coeff * (mut_diff_total / num_matching)
+        child
     }
 
-    pub fn verify(&self) -> Result<(), String>
+    /// Like mate_multipoint, but matching genes are averaged instead of chosen
+    pub fn mate_multipoint_avg(&self, other: &Genome, fitness1: f64, fitness2: f64, new_id: i32) -> Genome
     {
-        //Check each gene's nodes
-        for gene in &self.genes {
-            let i_node = &gene.link.i_node;
-            let o_node = &gene.link.o_node;
+        let (fitter, weaker) = if self.first_is_fitter(other, fitness1, fitness2) {
+            (self, other)
+        } else {
+            (other, self)
+        };
 
-            let mut i_node_found = false;
-            let mut o_node_found = false;
+        let mut child = Genome::offspring(fitter, Genome::blend_traits(fitter, weaker), new_id);
 
-            // look for i_node and o_node
-            for node in &self.nodes {
-                if i_node == node {
-                    i_node_found = true;
-                } else if o_node == node {
-                    o_node_found = true;
+        for pair in fitter.align(weaker).pairs {
+            match pair {
+                (Some(gene1), Some(gene2)) => child.inherit_gene(&Genome::average_gene(gene1, gene2), fitter),
+                //Disjoint and excess genes of the fitter parent
+                (Some(gene1), None) => child.inherit_gene(gene1, fitter),
+                _ => {}
+            }
+        }
+
+        child
+    }
+
+    /// Mates this genome with another one by cutting their aligned genes at a random
+    /// gene of the smaller parent: the genes before it come from the smaller parent,
+    /// the genes after it from the bigger one, and the gene at the cut is averaged
+    /// with its match. The traits of the parents are blended
+    pub fn mate_singlepoint(&self, other: &Genome, fitness1: f64, fitness2: f64, new_id: i32) -> Genome
+    {
+        let (fitter, weaker) = if self.first_is_fitter(other, fitness1, fitness2) {
+            (self, other)
+        } else {
+            (other, self)
+        };
+        let (smaller, bigger) = if self.genes.len() <= other.genes.len() {
+            (self, other)
+        } else {
+            (other, self)
+        };
+
+        let mut child = Genome::offspring(fitter, Genome::blend_traits(fitter, weaker), new_id);
+        if smaller.genes.is_empty() {
+            return child;
+        }
+
+        let crosspoint = rand::thread_rng().gen_range(0, smaller.genes.len());
+
+        //The number of genes of the smaller parent already passed
+        let mut gene_counter = 0;
+        for pair in smaller.align(bigger).pairs {
+            match pair {
+                (Some(gene1), _) if gene_counter < crosspoint => child.inherit_gene(gene1, smaller),
+                (Some(gene1), Some(gene2)) if gene_counter == crosspoint => {
+                    child.inherit_gene(&Genome::average_gene(gene1, gene2), smaller)
                 }
+                (Some(gene1), None) if gene_counter == crosspoint => child.inherit_gene(gene1, smaller),
+                (_, Some(gene2)) if gene_counter > crosspoint => child.inherit_gene(gene2, bigger),
+                _ => {}
+            }
+
+            if pair.0.is_some() {
+                gene_counter += 1;
             }
+        }
+
+        child
+    }
+
+    /// Mates this genome with another one, choosing multipoint crossover with
+    /// mate_multipoint_prob, otherwise averaging or single point crossover
+    /// in the ratio of their probabilities from the Env.
+    /// When both of these are 0, multipoint crossover is used
+    pub fn mate(&self, other: &Genome, fitness1: f64, fitness2: f64, new_id: i32, env: &Env) -> Genome
+    {
+        let other_probs = env.mate_multipoint_avg_prob + env.mate_singlepoint_prob;
+
+        if other_probs <= 0.0 || rand::random::<f64>() < env.mate_multipoint_prob {
+            self.mate_multipoint(other, fitness1, fitness2, new_id)
+        } else if rand::random::<f64>() < env.mate_multipoint_avg_prob / other_probs {
+            self.mate_multipoint_avg(other, fitness1, fitness2, new_id)
+        } else {
+            self.mate_singlepoint(other, fitness1, fitness2, new_id)
+        }
+    }
+
+    /// Applies one mutation to the genome: a new node, a new link or otherwise
+    /// the non-structural mutations, each one with its probability from the Env.
+    /// Structural mutations are recorded in the innovations of the generation.
+    /// cur_node_id and cur_innov_num are the last node id and innovation
+    /// number used in the population, new innovations advance them
+    pub fn mutate(&mut self, env: &Env, innovations: &mut InnovationTable, cur_node_id: &mut i32, cur_innov_num: &mut f64)
+    {
+        if rand::random::<f64>() < env.mutate_add_node_prob {
+            self.mutate_add_node(innovations, cur_node_id, cur_innov_num);
+            return;
+        }
+        if rand::random::<f64>() < env.mutate_add_link_prob {
+            self.mutate_add_link(env, innovations, cur_innov_num);
+            return;
+        }
+
+        if rand::random::<f64>() < env.mutate_random_trait_prob {
+            self.mutate_random_trait(env.trait_mutation_power, env.trait_param_mut_prob);
+        }
+        if rand::random::<f64>() < env.mutate_link_trait_prob {
+            self.mutate_link_trait(1, env.linktrait_mut_sig);
+        }
+        if rand::random::<f64>() < env.mutate_node_trait_prob {
+            self.mutate_node_trait(1, env.nodetrait_mut_sig);
+        }
+        if rand::random::<f64>() < env.mutate_link_weights_prob {
+            self.mutate_link_weights(env.weight_mut_power, 1.0, Mutator::Gaussian);
+        }
+        if rand::random::<f64>() < env.mutate_toggle_enable_prob {
+            self.mutate_toggle_enable(1);
+        }
+        if rand::random::<f64>() < env.mutate_gene_reenable_prob {
+            self.mutate_gene_reenable();
+        }
+        if rand::random::<f64>() < env.mutate_function_type_prob {
+            self.mutate_function_type();
+        }
+        if rand::random::<f64>() < env.mutate_aggregation_prob {
+            self.mutate_aggregation();
+        }
+        if rand::random::<f64>() < env.mutate_node_dynamics_prob {
+            self.mutate_node_dynamics(env.dynamics_mut_power);
+        }
+    }
+
+    pub fn randomize_traits(&mut self) {
+        let num_traits = self.traits.len();
+        let between: Range<usize> = Range::new(0, num_traits);
+        let mut rng = rand::thread_rng();
+
+        for node in &mut self.nodes {
+            let trait_num = between.ind_sample(&mut rng);
+            node.node_trait = Some(self.traits[trait_num].id);
+        }
+
+        for gene in &mut self.genes {
+            let trait_num = between.ind_sample(&mut rng);
+            gene.link.link_trait = Some(self.traits[trait_num].id);
+        }
+    }
+
+    pub fn get_last_node_id(&self) -> Option<i32>
+    {
+        self.nodes.last().map(|node| node.id)
+    }
+
+    pub fn get_last_gene_innovnum(&self) -> Option<f64>
+    {
+        self.genes.last().map(|gene| gene.get_innovation_num())
+    }
+
+    /// The distance between two genomes, measured as configured in the Env
+    pub fn compatibility(&self, other: &Genome, env: &Env) -> f64
+    {
+        Compatibility::from_env(env).distance(self, other)
+    }
+
+    /// The mean distance between the traits with the same id in the two genomes,
+    /// 0 if they have none in common
+    pub fn trait_distance(&self, other: &Genome) -> f64
+    {
+        let distances: Vec<f64> = self.traits.iter()
+            .filter_map(|gene_trait| other.traits.iter()
+                .find(|other_trait| other_trait.id == gene_trait.id)
+                .map(|other_trait| gene_trait.distance(other_trait)))
+            .collect();
+
+        if distances.is_empty() {
+            0.0
+        } else {
+            distances.iter().sum::<f64>() / distances.len() as f64
+        }
+    }
+
+    pub fn verify(&self) -> Result<(), String>
+    {
+        //Check each gene's nodes
+        for gene in &self.genes {
+            // look for i_node and o_node
+            let i_node_found = self.nodes.iter().any(|node| node.id == gene.link.i_node);
+            let o_node_found = self.nodes.iter().any(|node| node.id == gene.link.o_node);
 
             if !i_node_found {
                 return Err(format!("input node in link not found in node list for node: {:?}", gene));
@@ -311,10 +974,10 @@ impl Genome {
         //Check for NNodes being out of order
         let mut last_id: i32 = 0;
         for node in &self.nodes {
-            if node.borrow().id < last_id {
+            if node.id < last_id {
                 return Err(format!("Nodes out of order!"));
             }
-            last_id = node.borrow().id;
+            last_id = node.id;
         }
 
         //Make sure there are no duplicate genes
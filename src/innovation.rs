@@ -0,0 +1,123 @@
+/// The kind of structural mutation an Innovation records
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub enum InnovationType {
+    NewNode,
+    NewLink,
+}
+
+/// ---------------------------------------------
+/// INNOVATION STRUCT:
+///   Records a structural mutation, so that the
+///   same mutation happening again in the same
+///   generation gets the same innovation numbers
+///   and node id, and crossover can align them
+/// ---------------------------------------------
+#[derive(Debug, Clone, PartialEq)]
+pub struct Innovation {
+    pub innovation_type: InnovationType,
+    /// The two nodes the mutation is between
+    pub i_node: i32,
+    pub o_node: i32,
+    /// The innovation number of the new gene, or of the first of the two genes of a new node
+    pub innovation_num1: f64,
+    /// The innovation number of the second gene of a new node
+    pub innovation_num2: f64,
+    /// The weight of a new link
+    pub new_weight: f64,
+    /// The id of a new node
+    pub new_node_id: i32,
+    /// The innovation number of the gene split by a new node
+    pub old_innov_num: f64,
+    /// Tells if a new link is recurrent
+    pub recurrent: bool,
+}
+
+impl Innovation {
+    /// Records a new node splitting the gene old_innov_num between i_node and o_node
+    pub fn new_node(i_node: i32, o_node: i32, innovation_num1: f64, innovation_num2: f64, new_node_id: i32, old_innov_num: f64) -> Self
+    {
+        Innovation {
+            innovation_type: InnovationType::NewNode,
+            i_node,
+            o_node,
+            innovation_num1,
+            innovation_num2,
+            new_weight: 0.0,
+            new_node_id,
+            old_innov_num,
+            recurrent: false,
+        }
+    }
+
+    /// Records a new link from i_node to o_node
+    pub fn new_link(i_node: i32, o_node: i32, innovation_num: f64, new_weight: f64, recurrent: bool) -> Self
+    {
+        Innovation {
+            innovation_type: InnovationType::NewLink,
+            i_node,
+            o_node,
+            innovation_num1: innovation_num,
+            innovation_num2: 0.0,
+            new_weight,
+            new_node_id: 0,
+            old_innov_num: 0.0,
+            recurrent,
+        }
+    }
+}
+
+/// ---------------------------------------------
+/// INNOVATION TABLE STRUCT:
+///   The innovations of the current generation,
+///   looked up by mutation type, endpoints and
+///   split gene. It is cleared at every epoch
+/// ---------------------------------------------
+#[derive(Debug, Clone, Default)]
+pub struct InnovationTable {
+    innovations: Vec<Innovation>,
+}
+
+impl InnovationTable {
+    pub fn new() -> Self
+    {
+        InnovationTable {
+            innovations: Vec::new(),
+        }
+    }
+
+    pub fn len(&self) -> usize { self.innovations.len() }
+    pub fn is_empty(&self) -> bool { self.innovations.is_empty() }
+
+    pub fn add(&mut self, innovation: Innovation)
+    {
+        self.innovations.push(innovation)
+    }
+
+    /// Finds the new node that already split the gene old_innov_num between i_node and o_node
+    pub fn find_new_node(&self, i_node: i32, o_node: i32, old_innov_num: f64) -> Option<&Innovation>
+    {
+        self.innovations.iter().find(|innovation| {
+            innovation.innovation_type == InnovationType::NewNode
+                && innovation.i_node == i_node
+                && innovation.o_node == o_node
+                && innovation.old_innov_num == old_innov_num
+        })
+    }
+
+    /// Finds the new link already made from i_node to o_node
+    pub fn find_new_link(&self, i_node: i32, o_node: i32, recurrent: bool) -> Option<&Innovation>
+    {
+        self.innovations.iter().find(|innovation| {
+            innovation.innovation_type == InnovationType::NewLink
+                && innovation.i_node == i_node
+                && innovation.o_node == o_node
+                && innovation.recurrent == recurrent
+        })
+    }
+
+    /// Forgets all the innovations, at the end of a generation
+    pub fn clear(&mut self)
+    {
+        self.innovations.clear()
+    }
+}
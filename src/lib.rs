@@ -6,11 +6,13 @@ mod tests;
 pub mod env;
 pub mod population;
 pub mod species;
+pub mod speciation;
 pub mod organism;
 pub mod genome;
 pub mod node;
 pub mod link;
 pub mod network;
+pub mod innovation;
 
 pub enum Mutator {
     Gaussian,
@@ -1,61 +1,83 @@
-use genome::gene_trait::Trait;
-use node::Node;
-use std::rc::Rc;
-use std::cell::RefCell;
+use genome::gene_trait::{NUM_TRAIT_PARAMS, Trait};
+use network::plasticity;
 use std::cmp::{Eq, PartialEq};
 
-#[derive(Clone)]
+#[derive(Debug, Clone)]
 pub struct Link {
     /// Weight of the connection
     pub weight: f64,
-    /// Node inputting into the link
-    pub i_node: Rc<RefCell<Node>>,
-    /// NNode that the link affects
-    pub o_node: Rc<RefCell<Node>>,
+    /// The id of the node inputting into the link
+    pub i_node: i32,
+    /// The id of the NNode that the link affects
+    pub o_node: i32,
     time_delay: bool,
     recurrent: bool,
 
-    pub link_trait: Option<Rc<RefCell<Trait>>>,
+    /// The id of the trait of parameters
+    pub link_trait: Option<i32>,
 
     /// The amount of weight adjustment
     added_weight: f64,
+
+    /// The learning parameters, derived from the link_trait
+    params: [f64; NUM_TRAIT_PARAMS],
 }
 
 impl Link {
-    pub fn new(link_trait: Option<Rc<RefCell<Trait>>>, weight: f64, i_node: Rc<RefCell<Node>>, o_node: Rc<RefCell<Node>>, recurrent: bool)
+    pub fn new(link_trait: Option<i32>, weight: f64, i_node: i32, o_node: i32, recurrent: bool)
                -> Self
     {
         Link {
             link_trait,
             weight,
             added_weight: 0.0,
+            params: [0.0; NUM_TRAIT_PARAMS],
             i_node,
             o_node,
             recurrent,
             time_delay: false,
         }
     }
-}
 
-use std::fmt::{Formatter, Debug};
-use std::fmt::Result as fmtResult;
+    pub fn is_recurrent(&self) -> bool { self.recurrent }
+    pub fn is_time_delayed(&self) -> bool { self.time_delay }
+    pub fn set_time_delay(&mut self, time_delay: bool) { self.time_delay = time_delay }
+    pub fn get_added_weight(&self) -> f64 { self.added_weight }
+    pub fn params(&self) -> &[f64; NUM_TRAIT_PARAMS] { &self.params }
 
-impl Debug for Link {
-    fn fmt(&self, f: &mut Formatter) -> fmtResult {
-        unimplemented!()
+    /// Copies the parameters of the link_trait, looked up in traits,
+    /// into the link's learning parameters
+    pub fn derive_trait(&mut self, traits: &[Trait])
+    {
+        self.params = self.link_trait
+            .and_then(|id| traits.iter().find(|link_trait| link_trait.id == id))
+            .map_or([0.0; NUM_TRAIT_PARAMS], |link_trait| *link_trait.params());
+    }
+
+    /// Changes the weight according to the learning rules.
+    /// `active_in` and `active_out` are the activations at the two ends of the link,
+    /// `sensitization_rate` comes from the parameters of the output node.
+    /// The link parameters are used as follows:
+    ///   0: Hebbian learning rate
+    ///   1: presynaptic (depression) rate of the Hebbian rule
+    ///   2: habituation rate
+    pub fn adapt(&mut self, active_in: f64, active_out: f64, sensitization_rate: f64, max_weight: f64)
+    {
+        let old_weight = self.weight;
+
+        self.weight = plasticity::hebbian(self.weight, max_weight, active_in, active_out,
+                                          self.params[0], self.params[1]);
+        self.weight = plasticity::habituation(self.weight, active_in, self.params[2]);
+        self.weight = plasticity::sensitization(self.weight, max_weight, active_out, sensitization_rate);
+
+        self.added_weight += self.weight - old_weight;
     }
 }
 
 impl PartialEq for Link {
     fn eq(&self, other: &Link) -> bool {
         if self.weight != other.weight { return false; }
-        if self.link_trait.is_some() && other.link_trait.is_some() {
-            if self.link_trait.ne(&other.link_trait) {
-                return false;
-            }
-        } else if self.link_trait.is_some() || other.link_trait.is_some() {
-            return false;
-        }
+        if self.link_trait != other.link_trait { return false; }
 
         if self.i_node != other.i_node { return false }
         if self.o_node != other.o_node { return false }
@@ -1,7 +0,0 @@
-pub struct Network {}
-
-impl Network {
-    pub fn new() -> Self {
-        Network {}
-    }
-}
\ No newline at end of file
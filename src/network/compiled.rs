@@ -0,0 +1,278 @@
+use network::Network;
+use node::{FunctionType, AggregationType};
+use std::collections::VecDeque;
+
+/// ---------------------------------------------
+/// COMPILED NETWORK STRUCT:
+///   A flat form of an acyclic Network. Nodes are
+///   stored in topological order and links are
+///   kept in contiguous vectors, so that an
+///   evaluation is a single pass over arrays
+/// ---------------------------------------------
+#[derive(Debug, Clone)]
+pub struct CompiledNetwork {
+    /// The id of the Network this was compiled from
+    pub net_id: i32,
+    /// Number of values expected by evaluate
+    num_inputs: usize,
+    /// Number of bias values, stored right after the inputs
+    num_biases: usize,
+    /// The position of a value that is always 0, stored right after the biases.
+    /// Links coming from neurons that never activate read from it
+    zero: usize,
+    /// For each computed node, in topological order, the range of its incoming links in sources and weights
+    node_links: Vec<(usize, usize)>,
+    /// For each computed node, its activation function
+    function_types: Vec<FunctionType>,
+    /// For each computed node, how its incoming signals are combined
+    aggregations: Vec<AggregationType>,
+    /// The position of the value each link reads from
+    sources: Vec<usize>,
+    /// The weight of each link
+    weights: Vec<f64>,
+    /// The position of the value of each output
+    outputs: Vec<usize>,
+    /// Total number of values, one for each sensor, the zero value and each computed node.
+    /// Computed nodes are stored after the zero value, in topological order
+    num_values: usize,
+}
+
+impl CompiledNetwork {
+    /// Compiles a Network. Returns an error if the Network has cycles or
+    /// time delayed links, or if an output cannot be reached from the sensors
+    pub fn new(network: &Network) -> Result<Self, String>
+    {
+        //The position of the value of each node, if it has one
+        let mut slots: Vec<Option<usize>> = vec![None; network.all_nodes.len()];
+        let mut num_values = 0;
+
+        //Sensors come first, inputs then biases
+        for &sensor in network.inputs.iter().chain(network.biases.iter()) {
+            slots[sensor] = Some(num_values);
+            num_values += 1;
+        }
+        let zero = num_values;
+        num_values += 1;
+
+        //Count the incoming links coming from other neurons
+        let mut in_degree: Vec<usize> = vec![0; network.all_nodes.len()];
+        for (position, node) in network.all_nodes.iter().enumerate() {
+            if node.is_sensor() {
+                continue;
+            }
+
+            for &link in node.incoming() {
+                if network.links[link].is_time_delayed() {
+                    return Err(format!("Network {} has time delayed links and cannot be compiled", network.net_id));
+                }
+                if !network.all_nodes[network.link_ends[link].0].is_sensor() {
+                    in_degree[position] += 1;
+                }
+            }
+        }
+
+        //Sort the neurons topologically
+        let mut queue: VecDeque<usize> = (0..network.all_nodes.len())
+            .filter(|&node| !network.all_nodes[node].is_sensor() && in_degree[node] == 0)
+            .collect();
+        let mut ordered = Vec::new();
+
+        while let Some(node) = queue.pop_front() {
+            for &link in network.all_nodes[node].outgoing() {
+                let o_node = network.link_ends[link].1;
+                if network.all_nodes[o_node].is_sensor() {
+                    continue;
+                }
+                in_degree[o_node] -= 1;
+                if in_degree[o_node] == 0 {
+                    queue.push_back(o_node);
+                }
+            }
+            ordered.push(node);
+        }
+
+        let neurons_n = network.all_nodes.iter().filter(|node| !node.is_sensor()).count();
+        if ordered.len() != neurons_n {
+            return Err(format!("Network {} is recurrent and cannot be compiled", network.net_id));
+        }
+
+        let mut compiled = CompiledNetwork {
+            net_id: network.net_id,
+            num_inputs: network.inputs.len(),
+            num_biases: network.biases.len(),
+            zero,
+            node_links: Vec::new(),
+            function_types: Vec::new(),
+            aggregations: Vec::new(),
+            sources: Vec::new(),
+            weights: Vec::new(),
+            outputs: Vec::new(),
+            num_values: 0,
+        };
+
+        //Lay out the links of each neuron. Neurons that no signal can reach never
+        //activate and are left out, their output is always 0. Like in the Network,
+        //their links still send a 0 signal to the neurons that do activate
+        for &position in &ordered {
+            let node = &network.all_nodes[position];
+            let start = compiled.sources.len();
+            let mut reached = false;
+
+            for &link in node.incoming() {
+                let source = match slots[network.link_ends[link].0] {
+                    Some(source) => {
+                        reached = true;
+                        source
+                    }
+                    None => zero,
+                };
+                compiled.sources.push(source);
+                compiled.weights.push(network.links[link].weight);
+            }
+
+            if !reached {
+                compiled.sources.truncate(start);
+                compiled.weights.truncate(start);
+                continue;
+            }
+            let end = compiled.sources.len();
+
+            slots[position] = Some(num_values);
+            compiled.node_links.push((start, end));
+            compiled.function_types.push(node.function_type());
+            compiled.aggregations.push(node.aggregation());
+            num_values += 1;
+        }
+
+        for &output in &network.outputs {
+            match slots[output] {
+                Some(slot) => compiled.outputs.push(slot),
+                None => return Err(format!("Output {} of network {} cannot be reached",
+                                           network.all_nodes[output].id, network.net_id)),
+            }
+        }
+
+        compiled.num_values = num_values;
+
+        Ok(compiled)
+    }
+
+    pub fn inputs_n(&self) -> usize { self.num_inputs }
+    pub fn outputs_n(&self) -> usize { self.outputs.len() }
+
+    /// Evaluates the network on one set of inputs, returning the outputs in order.
+    /// Every neuron sees the final value of the nodes before it, so this is the value
+    /// the Network settles to when activated as many times as it is deep, not the
+    /// result of a single activate, which ignores the hidden nodes that have not fired yet.
+    /// Panics if the number of inputs does not match the network
+    pub fn evaluate(&self, inputs: &[f64]) -> Vec<f64>
+    {
+        assert_eq!(inputs.len(), self.num_inputs, "wrong number of inputs for compiled network {}", self.net_id);
+
+        let mut values = vec![0.0; self.num_values];
+        values[..self.num_inputs].copy_from_slice(inputs);
+        for bias in &mut values[self.num_inputs..self.num_inputs + self.num_biases] {
+            *bias = 1.0;
+        }
+
+        let first_computed = self.zero + 1;
+        let mut signals = Vec::new();
+        for (node, &(start, end)) in self.node_links.iter().enumerate() {
+            let links = self.sources[start..end].iter().zip(&self.weights[start..end]);
+
+            let activesum = if self.aggregations[node] == AggregationType::Sum {
+                links.map(|(source, weight)| weight * values[*source]).sum()
+            } else {
+                signals.clear();
+                signals.extend(links.map(|(source, weight)| weight * values[*source]));
+                self.aggregations[node].apply(&signals)
+            };
+
+            values[first_computed + node] = self.function_types[node].apply(activesum);
+        }
+
+        self.outputs.iter().map(|&output| values[output]).collect()
+    }
+
+    /// Evaluates the network on many sets of inputs at once, one per row,
+    /// returning one row of outputs for each row of inputs.
+    /// Values are laid out sample by sample for each node, so that the inner
+    /// loops run over contiguous memory and can be vectorized.
+    /// Panics if the number of inputs in a row does not match the network
+    pub fn evaluate_batch<R: AsRef<[f64]>>(&self, rows: &[R]) -> Vec<Vec<f64>>
+    {
+        let samples = rows.len();
+        let mut values = vec![0.0; self.num_values * samples];
+
+        for (sample, row) in rows.iter().enumerate() {
+            let row = row.as_ref();
+            assert_eq!(row.len(), self.num_inputs, "wrong number of inputs for compiled network {}", self.net_id);
+            for (input, value) in row.iter().enumerate() {
+                values[input * samples + sample] = *value;
+            }
+        }
+        for bias in &mut values[self.num_inputs * samples..(self.num_inputs + self.num_biases) * samples] {
+            *bias = 1.0;
+        }
+
+        let first_computed = self.zero + 1;
+        for (node, &(start, end)) in self.node_links.iter().enumerate() {
+            //Every link reads from a value stored before the node's own values
+            let (computed, rest) = values.split_at_mut((first_computed + node) * samples);
+            let activesum = &mut rest[..samples];
+
+            let links = self.sources[start..end].iter().zip(&self.weights[start..end]);
+            let aggregation = self.aggregations[node];
+
+            match aggregation {
+                AggregationType::Median => {
+                    //The median needs all the signals of a sample together
+                    let mut signals = Vec::with_capacity(end - start);
+                    for (sample, sum) in activesum.iter_mut().enumerate() {
+                        signals.clear();
+                        signals.extend(links.clone().map(|(source, weight)| weight * computed[source * samples + sample]));
+                        *sum = aggregation.apply(&signals);
+                    }
+                }
+                _ => {
+                    let initial = match aggregation {
+                        AggregationType::Product => 1.0,
+                        AggregationType::Max => f64::NEG_INFINITY,
+                        AggregationType::Min => f64::INFINITY,
+                        _ => 0.0,
+                    };
+                    for sum in activesum.iter_mut() {
+                        *sum = initial;
+                    }
+
+                    for (source, weight) in links {
+                        let source_values = &computed[source * samples..(source + 1) * samples];
+                        let sums = activesum.iter_mut().zip(source_values);
+                        match aggregation {
+                            AggregationType::Product => for (sum, value) in sums { *sum *= weight * value },
+                            AggregationType::Max => for (sum, value) in sums { *sum = sum.max(weight * value) },
+                            AggregationType::Min => for (sum, value) in sums { *sum = sum.min(weight * value) },
+                            _ => for (sum, value) in sums { *sum += weight * value },
+                        }
+                    }
+
+                    if aggregation == AggregationType::Mean {
+                        let links_n = (end - start) as f64;
+                        for sum in activesum.iter_mut() {
+                            *sum /= links_n;
+                        }
+                    }
+                }
+            }
+
+            let function_type = self.function_types[node];
+            for value in activesum.iter_mut() {
+                *value = function_type.apply(*value);
+            }
+        }
+
+        (0..samples)
+            .map(|sample| self.outputs.iter().map(|&output| values[output * samples + sample]).collect())
+            .collect()
+    }
+}
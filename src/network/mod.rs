@@ -0,0 +1,294 @@
+pub mod compiled;
+pub mod plasticity;
+
+use self::compiled::CompiledNetwork;
+use node::{Node, NodePlace};
+use link::Link;
+
+/// How the nodes of a Network are updated at each activation
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub enum Dynamics {
+    /// The spreading activation of the original NEAT
+    Discrete,
+    /// Continuous time recurrent neurons (CTRNN), integrated with an Euler step of time_step
+    Ctrnn { time_step: f64 },
+}
+
+/// ---------------------------------------------
+/// NETWORK STRUCT:
+///   A Network is the phenotype of a Genome:
+///   the nodes and the enabled links decoded
+///   from it, ready to be activated
+/// ---------------------------------------------
+pub struct Network {
+    /// The id of the Genome this Network was decoded from
+    pub net_id: i32,
+    /// A list of all the nodes, links refer to them by position
+    all_nodes: Vec<Node>,
+    /// A list of all the links, nodes refer to them by position
+    links: Vec<Link>,
+    /// The positions of the input node and of the output node of each link
+    link_ends: Vec<(usize, usize)>,
+    /// The positions of the sensors receiving external input
+    inputs: Vec<usize>,
+    /// The positions of the bias sensors
+    biases: Vec<usize>,
+    /// The positions of the output nodes
+    outputs: Vec<usize>,
+    /// When adaptable, the link weights change at every activation
+    /// following the learning rules parameterized by the traits
+    adaptable: bool,
+    /// The maximum magnitude learning can give to a weight
+    max_weight: f64,
+    /// How the nodes are updated
+    dynamics: Dynamics,
+}
+
+impl Network {
+    pub fn new(net_id: i32) -> Self {
+        Network {
+            net_id,
+            all_nodes: Vec::new(),
+            links: Vec::new(),
+            link_ends: Vec::new(),
+            inputs: Vec::new(),
+            biases: Vec::new(),
+            outputs: Vec::new(),
+            adaptable: false,
+            max_weight: 8.0,
+            dynamics: Dynamics::Discrete,
+        }
+    }
+
+    pub fn get_dynamics(&self) -> Dynamics { self.dynamics }
+    pub fn set_dynamics(&mut self, dynamics: Dynamics) { self.dynamics = dynamics }
+
+    pub fn is_adaptable(&self) -> bool { self.adaptable }
+    pub fn set_adaptable(&mut self, adaptable: bool) { self.adaptable = adaptable }
+    pub fn set_max_weight(&mut self, max_weight: f64) { self.max_weight = max_weight }
+
+    /// Adds a node to the network, also registering it as input, bias or output
+    /// depending on its place. Returns the position of the node
+    pub fn add_node(&mut self, node: Node) -> usize
+    {
+        let position = self.all_nodes.len();
+        match node.node_place() {
+            NodePlace::Input => self.inputs.push(position),
+            NodePlace::Bias => self.biases.push(position),
+            NodePlace::Output => self.outputs.push(position),
+            NodePlace::Hidden => {}
+        }
+        self.all_nodes.push(node);
+
+        position
+    }
+
+    /// Adds a link between the nodes at the given positions,
+    /// adding it to the out node's incoming list and to the in node's outgoing list.
+    /// Returns the position of the link
+    pub fn add_link(&mut self, link: Link, in_node: usize, out_node: usize) -> usize
+    {
+        let position = self.links.len();
+        self.links.push(link);
+        self.link_ends.push((in_node, out_node));
+        self.all_nodes[out_node].add_incoming(position);
+        self.all_nodes[in_node].add_outgoing(position);
+
+        position
+    }
+
+    pub fn nodes_n(&self) -> usize { self.all_nodes.len() }
+    pub fn inputs_n(&self) -> usize { self.inputs.len() }
+    pub fn biases_n(&self) -> usize { self.biases.len() }
+    pub fn outputs_n(&self) -> usize { self.outputs.len() }
+
+    /// Loads the sensor values into the input nodes, in order. Bias nodes are always loaded with 1.0
+    pub fn load_sensors(&mut self, sensor_values: &[f64]) -> Result<(), String>
+    {
+        if sensor_values.len() != self.inputs.len() {
+            return Err(format!("Network {} has {} inputs but {} sensor values were given",
+                               self.net_id, self.inputs.len(), sensor_values.len()));
+        }
+
+        for (&input, value) in self.inputs.iter().zip(sensor_values) {
+            self.all_nodes[input].sensor_load(*value);
+        }
+
+        for &bias in &self.biases {
+            self.all_nodes[bias].sensor_load(1.0);
+        }
+
+        Ok(())
+    }
+
+    /// Returns the activation of the output nodes, in order
+    pub fn outputs(&self) -> Vec<f64>
+    {
+        self.outputs.iter()
+            .map(|&output| self.all_nodes[output].get_active_out())
+            .collect()
+    }
+
+    /// Tells if any output has not been activated yet
+    pub fn outputs_off(&self) -> bool
+    {
+        self.outputs.iter()
+            .any(|&output| self.all_nodes[output].get_activation_count() == 0)
+    }
+
+    /// Activates the net for one time step, then lets it learn if it is adaptable.
+    /// With discrete dynamics, the first activation runs until all outputs are active
+    /// and returns an error if the outputs could not be reached after 20 passes
+    pub fn activate(&mut self) -> Result<(), String>
+    {
+        if let Dynamics::Ctrnn { time_step } = self.dynamics {
+            self.integrate(time_step);
+        } else {
+            self.spread_activation()?;
+        }
+
+        if self.adaptable {
+            self.adapt();
+        }
+
+        Ok(())
+    }
+
+    /// Combines the weighted signals coming into a node with its aggregation function.
+    /// Also tells if any of the signals comes from a node that already has an output
+    fn incoming_activity(&self, node: usize, signals: &mut Vec<f64>) -> (f64, bool)
+    {
+        signals.clear();
+        let mut active = false;
+
+        let node = &self.all_nodes[node];
+        for &link in node.incoming() {
+            let in_node = &self.all_nodes[self.link_ends[link].0];
+            let link = &self.links[link];
+            if link.is_time_delayed() {
+                //A time delayed link carries the previous step's signal
+                //and cannot activate the node by itself
+                signals.push(link.weight * in_node.get_active_out_td());
+            } else {
+                //A recurrent link reads the latest activation of its input,
+                //which is one step behind when the input is downstream
+                signals.push(link.weight * in_node.get_active_out());
+                if in_node.is_active() || in_node.is_sensor() {
+                    active = true;
+                }
+            }
+        }
+
+        (node.aggregation().apply(signals), active)
+    }
+
+    /// Advances all the continuous time nodes by one Euler step
+    fn integrate(&mut self, time_step: f64)
+    {
+        let mut signals = Vec::new();
+
+        //All the nodes see the state from before the step
+        let activesums: Vec<f64> = (0..self.all_nodes.len())
+            .map(|node| self.incoming_activity(node, &mut signals).0)
+            .collect();
+
+        for (node, activesum) in self.all_nodes.iter_mut().zip(activesums) {
+            if !node.is_sensor() {
+                node.integrate(activesum, time_step);
+            }
+        }
+    }
+
+    /// Runs the spreading activation of the original NEAT
+    fn spread_activation(&mut self) -> Result<(), String>
+    {
+        //Used in case the output is somehow truncated from the network
+        let mut abort_count = 0;
+
+        //Make sure we at least activate once
+        let mut one_time = false;
+
+        //Keep activating until all the outputs have become active
+        //(This only happens on the first activation, because after that they
+        // are always active)
+        while self.outputs_off() || !one_time {
+            abort_count += 1;
+
+            if abort_count == 20 {
+                return Err(format!("Network {} could not activate its outputs", self.net_id));
+            }
+
+            //For each node, combine its incoming activation
+            let mut signals = Vec::new();
+            for node in 0..self.all_nodes.len() {
+                //Ignore SENSORS
+                if self.all_nodes[node].is_sensor() {
+                    continue;
+                }
+
+                self.all_nodes[node].reset_activesum();
+
+                let (activesum, active) = self.incoming_activity(node, &mut signals);
+                self.all_nodes[node].add_to_activesum(activesum, active);
+            }
+
+            //Now activate all the non-sensor nodes off their incoming activation
+            for node in &mut self.all_nodes {
+                if !node.is_sensor() {
+                    node.activate();
+                }
+            }
+
+            one_time = true;
+        }
+
+        Ok(())
+    }
+
+    /// Changes the weights of the links following the learning rules
+    fn adapt(&mut self)
+    {
+        let mut inputs = Vec::new();
+
+        for node in &self.all_nodes {
+            if node.is_sensor() {
+                continue;
+            }
+
+            //A recurrent link learns from what its input sent, one step ago
+            inputs.clear();
+            inputs.extend(node.incoming().iter().map(|&link| {
+                let in_node = &self.all_nodes[self.link_ends[link].0];
+                if self.links[link].is_recurrent() {
+                    in_node.get_last_activation()
+                } else {
+                    in_node.get_active_out()
+                }
+            }));
+
+            let active_out = node.get_active_out();
+            let sensitization_rate = node.params()[0];
+            for (&link, active_in) in node.incoming().iter().zip(&inputs) {
+                self.links[link].adapt(*active_in, active_out, sensitization_rate, self.max_weight);
+            }
+        }
+    }
+
+    /// Resets the activation memory of all the nodes, so that recurrent and
+    /// time delayed links start from a clean state (e.g. between episodes)
+    pub fn flush(&mut self)
+    {
+        for node in &mut self.all_nodes {
+            node.flush();
+        }
+    }
+
+    /// Compiles the network into a flat form for fast evaluation.
+    /// Only acyclic networks without time delayed links can be compiled
+    pub fn compile(&self) -> Result<CompiledNetwork, String>
+    {
+        CompiledNetwork::new(self)
+    }
+
+    pub fn links_n(&self) -> usize { self.links.len() }
+}
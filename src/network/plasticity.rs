@@ -0,0 +1,56 @@
+// Learning rules used by adaptive networks to change their link weights
+// during activation. A rate of 0 leaves the weight unchanged. Both bounding
+// rules keep the weights within the same bound, see weight_bound.
+
+/// The largest magnitude the learning rules let a weight reach: max_weight,
+/// but at least 5 as in the original NEAT
+pub fn weight_bound(max_weight: f64) -> f64
+{
+    if max_weight < 5.0 { 5.0 } else { max_weight }
+}
+
+/// The Hebbian rule of the original NEAT: coincident activity strengthens
+/// the link, presynaptic activity without postsynaptic activity weakens it.
+/// The weight is kept within weight_bound(max_weight)
+pub fn hebbian(weight: f64, max_weight: f64, active_in: f64, active_out: f64, hebb_rate: f64, pre_rate: f64) -> f64
+{
+    if hebb_rate == 0.0 && pre_rate == 0.0 {
+        return weight;
+    }
+
+    let max_weight = weight_bound(max_weight);
+    let weight = weight.clamp(-max_weight, max_weight);
+
+    let magnitude = weight.abs();
+    let top_weight = (magnitude + 2.0).min(max_weight);
+
+    if weight >= 0.0 {
+        let delta = hebb_rate * (max_weight - magnitude) * active_in * active_out
+            + pre_rate * top_weight * active_in * (active_out - 1.0);
+        magnitude + delta
+    } else {
+        let delta = pre_rate * (max_weight - magnitude) * active_in * (1.0 - active_out)
+            - hebb_rate * (top_weight + 2.0) * active_in * active_out;
+        -(magnitude + delta)
+    }
+}
+
+/// Habituation: repeated presynaptic activity makes the link fade towards 0
+pub fn habituation(weight: f64, active_in: f64, rate: f64) -> f64
+{
+    weight - rate * active_in.abs() * weight
+}
+
+/// Sensitization: postsynaptic activity makes the link stronger, whatever
+/// the presynaptic activity. The magnitude never goes beyond weight_bound(max_weight)
+pub fn sensitization(weight: f64, max_weight: f64, active_out: f64, rate: f64) -> f64
+{
+    let max_weight = weight_bound(max_weight);
+    let magnitude = weight.abs();
+    if magnitude >= max_weight {
+        return weight;
+    }
+
+    let magnitude = magnitude + rate * active_out.abs() * (max_weight - magnitude);
+    if weight < 0.0 { -magnitude } else { magnitude }
+}
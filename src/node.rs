@@ -1,9 +1,9 @@
 use genome::gene_trait::{NUM_TRAIT_PARAMS,Trait};
-use link::Link;
-use std::rc::Rc;
-use std::cell::RefCell;
 use std::cmp::{Eq, PartialEq};
 
+/// The smallest time constant a node can have
+pub const MIN_TIME_CONSTANT: f64 = 0.01;
+
 #[derive(Debug, Copy, Clone, Eq, PartialEq)]
 pub enum NodeType {
     Neuron,
@@ -21,6 +21,94 @@ pub enum NodePlace {
 #[derive(Debug, Copy, Clone, Eq, PartialEq)]
 pub enum FunctionType {
     Sigmoid,
+    Tanh,
+    Relu,
+    Identity,
+    Step,
+    Gaussian,
+    Sine,
+    Absolute,
+    Clamped,
+}
+
+impl FunctionType {
+    /// All the available activation functions
+    pub const ALL: [FunctionType; 9] = [
+        FunctionType::Sigmoid,
+        FunctionType::Tanh,
+        FunctionType::Relu,
+        FunctionType::Identity,
+        FunctionType::Step,
+        FunctionType::Gaussian,
+        FunctionType::Sine,
+        FunctionType::Absolute,
+        FunctionType::Clamped,
+    ];
+
+    /// Applies the activation function to the incoming activity
+    pub fn apply(&self, activesum: f64) -> f64 {
+        match *self {
+            // Sigmoidal activation with the steepness used by the original NEAT
+            FunctionType::Sigmoid => 1.0 / (1.0 + (-4.924273 * activesum).exp()),
+            FunctionType::Tanh => activesum.tanh(),
+            FunctionType::Relu => activesum.max(0.0),
+            FunctionType::Identity => activesum,
+            FunctionType::Step => if activesum > 0.0 { 1.0 } else { 0.0 },
+            FunctionType::Gaussian => (-activesum * activesum).exp(),
+            FunctionType::Sine => activesum.sin(),
+            FunctionType::Absolute => activesum.abs(),
+            FunctionType::Clamped => activesum.clamp(-1.0, 1.0),
+        }
+    }
+}
+
+#[derive(Debug, Copy, Clone, Eq, PartialEq)]
+pub enum AggregationType {
+    Sum,
+    Product,
+    Max,
+    Min,
+    Mean,
+    Median,
+}
+
+impl AggregationType {
+    /// All the available aggregation functions
+    pub const ALL: [AggregationType; 6] = [
+        AggregationType::Sum,
+        AggregationType::Product,
+        AggregationType::Max,
+        AggregationType::Min,
+        AggregationType::Mean,
+        AggregationType::Median,
+    ];
+
+    /// Combines the weighted incoming signals into the incoming activity.
+    /// A node without incoming signals has no activity
+    pub fn apply(&self, inputs: &[f64]) -> f64 {
+        if inputs.is_empty() {
+            return 0.0;
+        }
+
+        match *self {
+            AggregationType::Sum => inputs.iter().sum(),
+            AggregationType::Product => inputs.iter().product(),
+            AggregationType::Max => inputs.iter().cloned().fold(f64::NEG_INFINITY, f64::max),
+            AggregationType::Min => inputs.iter().cloned().fold(f64::INFINITY, f64::min),
+            AggregationType::Mean => inputs.iter().sum::<f64>() / inputs.len() as f64,
+            AggregationType::Median => {
+                let mut sorted = inputs.to_vec();
+                //NaN signals are sorted last instead of panicking
+                sorted.sort_by(|a, b| a.total_cmp(b));
+                let middle = sorted.len() / 2;
+                if sorted.len() % 2 == 0 {
+                    (sorted[middle - 1] + sorted[middle]) / 2.0
+                } else {
+                    sorted[middle]
+                }
+            }
+        }
+    }
 }
 
 #[derive(Debug)]
@@ -38,8 +126,8 @@ pub struct Node {
     /// The innode then needs to send from TWO time steps ago
     last_activation2: f64,
 
-    /// Points to a trait of parameters
-    pub node_trait: Option<Rc<RefCell<Trait>>>,
+    /// The id of the trait of parameters
+    pub node_trait: Option<i32>,
 
 
 //    /// Used for Gene decoding
@@ -55,15 +143,19 @@ pub struct Node {
 
     /// type is either SIGMOID ..or others that can be added
     function_type: FunctionType,
+    /// How the weighted incoming signals are combined into the activesum
+    aggregation: AggregationType,
     /// type is either NEURON or SENSOR
     node_type: NodeType,
     /// Used for genetic marking of nodes
     node_place: NodePlace,
 
-    /// A list of incoming weighted signals from other nodes
-    incoming: Vec<Link>,
-    /// A list of links carrying this node's signal
-    outgoing: Vec<Link>,
+    /// The positions of the links bringing weighted signals from other nodes,
+    /// in the list of links of the owning Network
+    incoming: Vec<usize>,
+    /// The positions of the links carrying this node's signal,
+    /// in the list of links of the owning Network
+    outgoing: Vec<usize>,
 
 
     /// The incoming activity before being processed
@@ -79,10 +171,18 @@ pub struct Node {
     //   sensitization, or Hebbian-type processes
 
     params: [f64; NUM_TRAIT_PARAMS],
+
+    // ************ CTRNN PARAMETERS ***********
+    // Only used when the network runs in continuous time
+
+    /// How fast the node's state follows its input
+    time_constant: f64,
+    /// Added to the incoming activity before the activation function
+    bias: f64,
 }
 
 impl Node {
-    pub fn new(id: i32, node_trait: Option<Rc<RefCell<Trait>>>, node_type: NodeType, node_place: NodePlace) -> Self {
+    pub fn new(id: i32, node_trait: Option<i32>, node_type: NodeType, node_place: NodePlace) -> Self {
         Node {
             id,
             activation_count: 0,
@@ -93,6 +193,7 @@ impl Node {
             override_node: false,
             override_value: 0.0,
             function_type: FunctionType::Sigmoid,
+            aggregation: AggregationType::Sum,
             node_type,
             node_place,
             incoming: Vec::new(),
@@ -101,6 +202,8 @@ impl Node {
             activation: 0.0,
             active_flag: false,
             params: [0.0; NUM_TRAIT_PARAMS],
+            time_constant: 1.0,
+            bias: 0.0,
         }
     }
 
@@ -110,11 +213,12 @@ impl Node {
             activation_count: self.activation_count,
             last_activation: 0.0,
             last_activation2: 0.0,
-            node_trait: self.node_trait.clone(),
+            node_trait: self.node_trait,
             frozen: false,
             override_node: false,
             override_value: self.override_value,
             function_type: self.function_type,
+            aggregation: self.aggregation,
             node_type: self.node_type,
             node_place: self.node_place,
             incoming: self.incoming.clone(),
@@ -123,25 +227,166 @@ impl Node {
             activation: 0.0,
             active_flag: false,
             params: self.params,
+            time_constant: self.time_constant,
+            bias: self.bias,
+        }
+    }
+
+    pub fn node_type(&self) -> NodeType { self.node_type }
+    pub fn function_type(&self) -> FunctionType { self.function_type }
+    pub fn set_function_type(&mut self, function_type: FunctionType) { self.function_type = function_type }
+    pub fn aggregation(&self) -> AggregationType { self.aggregation }
+    pub fn set_aggregation(&mut self, aggregation: AggregationType) { self.aggregation = aggregation }
+    pub fn is_frozen(&self) -> bool { self.frozen }
+    pub fn node_place(&self) -> NodePlace { self.node_place }
+
+    pub fn incoming(&self) -> &Vec<usize> { &self.incoming }
+    pub fn outgoing(&self) -> &Vec<usize> { &self.outgoing }
+
+    pub fn add_incoming(&mut self, link: usize) { self.incoming.push(link) }
+    pub fn add_outgoing(&mut self, link: usize) { self.outgoing.push(link) }
+
+    pub fn is_sensor(&self) -> bool { self.node_type == NodeType::Sensor }
+    pub fn is_active(&self) -> bool { self.active_flag }
+    pub fn get_activation_count(&self) -> u32 { self.activation_count }
+
+    /// Returns the activation, or 0 if the node has never been activated
+    pub fn get_active_out(&self) -> f64
+    {
+        if self.activation_count > 0 {
+            self.activation
+        } else {
+            0.0
         }
     }
+
+    pub fn get_last_activation(&self) -> f64 { self.last_activation }
+
+    pub fn get_time_constant(&self) -> f64 { self.time_constant }
+    pub fn get_bias(&self) -> f64 { self.bias }
+
+    /// Sets the time constant, which is kept above MIN_TIME_CONSTANT
+    pub fn set_time_constant(&mut self, time_constant: f64)
+    {
+        self.time_constant = time_constant.max(MIN_TIME_CONSTANT);
+    }
+
+    pub fn set_bias(&mut self, bias: f64) { self.bias = bias }
+    pub fn params(&self) -> &[f64; NUM_TRAIT_PARAMS] { &self.params }
+
+    /// Copies the parameters of the node_trait, looked up in traits,
+    /// into the node's learning parameters
+    pub fn derive_trait(&mut self, traits: &[Trait])
+    {
+        self.params = self.node_trait
+            .and_then(|id| traits.iter().find(|node_trait| node_trait.id == id))
+            .map_or([0.0; NUM_TRAIT_PARAMS], |node_trait| *node_trait.params());
+    }
+
+    /// Returns the activation from the previous step, used by time delayed links
+    pub fn get_active_out_td(&self) -> f64
+    {
+        if self.activation_count > 1 {
+            self.last_activation
+        } else {
+            0.0
+        }
+    }
+
+    /// Resets all the activation memory of the node, as if it was never activated
+    pub fn flush(&mut self)
+    {
+        self.activation_count = 0;
+        self.activation = 0.0;
+        self.last_activation = 0.0;
+        self.last_activation2 = 0.0;
+        self.activesum = 0.0;
+        self.active_flag = false;
+    }
+
+    /// Loads a value into a sensor, returns false if the node is not a sensor
+    pub fn sensor_load(&mut self, value: f64) -> bool
+    {
+        if !self.is_sensor() {
+            return false;
+        }
+
+        //Time delay memory
+        self.last_activation2 = self.last_activation;
+        self.last_activation = self.activation;
+
+        //Puts sensor into next time-step
+        self.activation_count += 1;
+        self.activation = value;
+
+        true
+    }
+
+    /// Clears the incoming activity before a new activation pass
+    pub fn reset_activesum(&mut self)
+    {
+        self.activesum = 0.0;
+        self.active_flag = false;
+    }
+
+    /// Adds a weighted incoming signal. `active` tells if the signal comes
+    /// from a node that already has an output
+    pub fn add_to_activesum(&mut self, amount: f64, active: bool)
+    {
+        self.activesum += amount;
+        if active {
+            self.active_flag = true;
+        }
+    }
+
+    /// Integrates the continuous time state of the node over time_step with the Euler method:
+    ///   time_constant * d(activation)/dt = -activation + f(bias + activesum)
+    pub fn integrate(&mut self, activesum: f64, time_step: f64)
+    {
+        self.activesum = activesum;
+
+        let target = self.function_type.apply(self.bias + activesum);
+
+        self.last_activation2 = self.last_activation;
+        self.last_activation = self.activation;
+
+        self.activation += time_step / self.time_constant * (target - self.activation);
+        self.activation_count += 1;
+    }
+
+    /// Activates the node off its incoming activity, only if some active signal reached it
+    pub fn activate(&mut self)
+    {
+        if !self.active_flag {
+            return;
+        }
+
+        //Keep a memory of activations for potential time delayed connections
+        self.last_activation2 = self.last_activation;
+        self.last_activation = self.activation;
+
+        //If the node is being overridden from outside, stick in the override value
+        if self.override_node {
+            self.activation = self.override_value;
+            self.override_node = false;
+        } else {
+            self.activation = self.function_type.apply(self.activesum);
+        }
+
+        //Increment the activation_count
+        //First activation cannot be from nothing!!
+        self.activation_count += 1;
+    }
 }
 
 impl PartialEq for Node {
     fn eq(&self, other: &Node) -> bool {
         if self.id != other.id { return false }
-        if self.node_trait.is_some() && other.node_trait.is_some() {
-            let node_trait_1 = self.node_trait.clone().unwrap();
-            let node_trait_2 = other.node_trait.clone().unwrap();
-            if node_trait_1.borrow().id != node_trait_2.borrow().id {
-                return false;
-            }
-        } else if self.node_trait.is_some() || other.node_trait.is_some() {
-            return false;
-        }
+        if self.node_trait != other.node_trait { return false }
 
         if self.override_node != other.override_node { return false }
         if self.function_type != other.function_type { return false }
+        if self.aggregation != other.aggregation { return false }
         if self.node_type != other.node_type { return false }
         if self.node_place != other.node_place { return false }
         if self.incoming.len() != other.incoming.len() { return false }
@@ -47,12 +47,14 @@ pub struct Organism {
 impl Organism {
     pub fn new(fitness: f64, genome: Box<Genome>, generation: usize) -> Self
     {
+        let network = genome.genesis(genome.id);
+
         Organism {
             fitness,
             orig_fitness: fitness,
             error: 0.0,
             winner: false,
-            network: Network::new(),
+            network,
             genome,
             species: None,
             expected_offspring: 0.0,
@@ -80,6 +82,9 @@ impl Organism {
         self.species.is_some()
     }
 
+    pub fn network(&self) -> &Network { &self.network }
+    pub fn network_mut(&mut self) -> &mut Network { &mut self.network }
+
     pub fn get_fitness(&self) -> f64
     {
         self.fitness
@@ -91,5 +96,20 @@ impl Organism {
     }
 
     pub fn set_champion(&mut self, champion: bool) { self.champion = champion; }
+    pub fn is_champion(&self) -> bool { self.champion }
+    pub fn get_super_champ_offspring(&self) -> i32 { self.super_champ_offspring }
+    pub fn set_super_champ_offspring(&mut self, offspring: i32) { self.super_champ_offspring = offspring; }
+    pub fn is_pop_champ(&self) -> bool { self.pop_champ }
+    pub fn set_pop_champ(&mut self, pop_champ: bool) { self.pop_champ = pop_champ; }
+    pub fn is_pop_champ_child(&self) -> bool { self.pop_champ_child }
+
+    /// Marks the exact copy of the population champion, remembering its fitness
+    pub fn set_pop_champ_child(&mut self, high_fit: f64)
+    {
+        self.pop_champ_child = true;
+        self.high_fit = high_fit;
+    }
+
     pub fn set_elimination(&mut self, elimination: bool) { self.eliminate = elimination; }
+    pub fn is_eliminated(&self) -> bool { self.eliminate }
 }
@@ -6,6 +6,9 @@ use std::rc::Rc;
 use std::cell::RefCell;
 use Mutator;
 use env::Env;
+use innovation::InnovationTable;
+use speciation::{Speciation, ThresholdSpeciation};
+use rand;
 
 /// ---------------------------------------------
 /// POPULATION STRUCT:
@@ -19,12 +22,14 @@ pub struct Population {
     pub species: Vec<Rc<RefCell<Species>>>,
 
     // ******* Member variables used during reproduction *******
-    ///// For holding the genetic innovations of the newest generation
-    //innovations: std::Vec<Box<Innovations>>,
+    /// For holding the genetic innovations of the newest generation
+    innovations: InnovationTable,
     /// Current label number available
     cur_node_id: i32,
     cur_innov_num: f64,
     last_species: usize,
+    /// How the organisms are divided into species
+    speciation: Box<dyn Speciation>,
 
     // ******* Fitness Statistics *******
     mean_fitness: f64,
@@ -41,14 +46,23 @@ pub struct Population {
 }
 
 impl Population {
+    /// A population speciated with the NEAT compatibility threshold of the Env
     pub fn new(start_genome: &Genome, pop_size: usize, env: &Env) -> Self
+    {
+        Population::with_speciation(start_genome, pop_size, env, Box::new(ThresholdSpeciation::new(env.compat_threshold)))
+    }
+
+    /// A population speciated with the given strategy
+    pub fn with_speciation(start_genome: &Genome, pop_size: usize, env: &Env, speciation: Box<dyn Speciation>) -> Self
     {
         let mut population = Population {
             organisms: Vec::new(),
             species: Vec::new(),
+            innovations: InnovationTable::new(),
             cur_node_id: 0,
             cur_innov_num: 0.0,
             last_species: 0,
+            speciation,
             mean_fitness: 0.0,
             variance: 0.0,
             standard_deviation: 0.0,
@@ -76,37 +90,15 @@ impl Population {
         population
     }
 
+    /// Places the organisms in species with the speciation strategy in use
     pub fn speciate(&mut self, env: &Env)
     {
-        // Species counter
-        let mut counter: usize = 0;
-
-        for ref mut organism in &self.organisms {
-            for current_species in self.species.iter_mut() {
-                let comparison_organism = current_species.borrow().organisms.first().unwrap().clone();
-
-                if organism.borrow().genome.compatibility(&comparison_organism.borrow().genome, env) < env.compat_threshold {
-                    // Found compatible species, so add this organism to it
-                    current_species.borrow_mut().add_organism(organism.clone());
-                    organism.borrow_mut().set_species(current_species.clone());
-                    // The search is over
-                    break;
-                }
-            }
-
-            if !organism.borrow().has_species() {
-                counter += 1;
-                let mut new_species = Species::new(counter);
-                new_species.add_organism(organism.clone());
-                let new_species = Rc::new(RefCell::new(new_species));
-                organism.borrow_mut().set_species(new_species.clone());
-                self.species.push(new_species);
-            }
-        }
-
-        self.last_species = counter;
+        self.speciation.speciate(&self.organisms, &mut self.species, &mut self.last_species, env);
     }
 
+    /// The compatibility threshold of the speciation strategy, if it uses one
+    pub fn get_compat_threshold(&self) -> Option<f64> { self.speciation.compat_threshold() }
+
     pub fn verify(&self) -> Result<(), String>
     {
         for ref organism in &self.organisms {
@@ -130,10 +122,9 @@ impl Population {
         //These need to use ORIGINAL fitness
         //sorted_species.qsort(order_species);
         sorted_species.sort_by(|a, b| {
-            a.borrow().max_fitness
-                .partial_cmp(&b.borrow().max_fitness)
+            b.borrow().max_fitness
+                .partial_cmp(&a.borrow().max_fitness)
                 .unwrap()
-                //.reverse()
         });
 
         //Flag the lowest performing species over age 20 every 30 generations
@@ -149,7 +140,9 @@ impl Population {
         }
 
         println!("Number of species: {}", self.species.len());
-        println!("compat_treshold: {}", env.compat_threshold);
+        if let Some(compat_threshold) = self.get_compat_threshold() {
+            println!("compat_treshold: {}", compat_threshold);
+        }
 
         // Use Species' ages to modify the objective fitness of organisms in other words,
         // make it more fair for younger species so they have a chance to take hold.
@@ -231,9 +224,161 @@ impl Population {
             let org_a_fitness = org_a.first().unwrap().borrow().orig_fitness;
             let org_b_fitness = org_b.first().unwrap().borrow().orig_fitness;
             {
-                org_a_fitness.partial_cmp(&org_b_fitness)
+                org_b_fitness.partial_cmp(&org_a_fitness)
                 .unwrap()
             }
         });
+
+        //Check for Population-level stagnation
+        {
+            let best_species = sorted_species[0].borrow();
+            let mut pop_champ = best_species.organisms[0].borrow_mut();
+            pop_champ.set_pop_champ(true);
+            if pop_champ.orig_fitness > self.highest_fitness {
+                self.highest_fitness = pop_champ.orig_fitness;
+                self.highest_last_changed = 0;
+            } else {
+                self.highest_last_changed += 1;
+            }
+        }
+
+        //After too long without improvement, use delta coding
+        if self.highest_last_changed >= env.dropoff_age as u32 + 5 {
+            self.highest_last_changed = 0;
+            self.delta_code(&sorted_species, total_organisms);
+        } else if env.babies_stolen > 0 {
+            self.steal_babies(&sorted_species, env);
+        }
+
+        //Perform reproduction. Reproduction is done on a per-Species
+        //basis (So this could be paralellized potentially)
+        let mut babies = Vec::with_capacity(total_organisms);
+        for species in &sorted_species {
+            babies.extend(species.borrow().reproduce(generation, env, &sorted_species, &mut self.innovations,
+                                                     &mut self.cur_node_id, &mut self.cur_innov_num));
+        }
+
+        //Kill off all the organisms marked for death. The champions always survive
+        for species in &self.species {
+            species.borrow_mut().remove_eliminated();
+        }
+
+        //The species get older, the new ones will not be aged
+        for species in &self.species {
+            species.borrow_mut().age += 1;
+        }
+
+        //Replace the generation and place the babies in the species
+        self.organisms.clear();
+        for (count, mut baby) in babies.into_iter().enumerate() {
+            baby.genome.id = count as i32;
+            baby.network_mut().net_id = count as i32;
+            self.organisms.push(Rc::new(RefCell::new(baby)));
+        }
+
+        self.speciate(env);
+
+        //The innovations only match within a generation
+        self.innovations.clear();
+    }
+
+    /// Delta coding: only the two best species reproduce, each with half of the
+    /// offspring, and all of them go to their champions as super champion offspring.
+    /// The two species are considered improving again
+    pub(crate) fn delta_code(&self, sorted_species: &[Rc<RefCell<Species>>], total_organisms: usize)
+    {
+        let half_pop = total_organisms / 2;
+
+        //With a single species, it gets the whole population
+        let shares = if sorted_species.len() > 1 {
+            [half_pop, total_organisms - half_pop]
+        } else {
+            [total_organisms, 0]
+        };
+
+        for (position, species) in sorted_species.iter().enumerate() {
+            let mut species = species.borrow_mut();
+            match shares.get(position) {
+                Some(&share) => {
+                    species.expected_offspring = share;
+                    species.age_of_last_improvement = species.age;
+                    species.organisms[0].borrow_mut().set_super_champ_offspring(share as i32);
+                }
+                //Get rid of all the other species
+                None => species.expected_offspring = 0,
+            }
+        }
+    }
+
+    /// Takes babies_stolen expected offspring away from the worst species older than 5,
+    /// and gives them to the champions of the best species that are still improving:
+    /// 1/5, 1/5 and 1/10 of them to the top three, up to 3 more to each following
+    /// species, and the rest to the best one. These become super champion offspring
+    pub(crate) fn steal_babies(&self, sorted_species: &[Rc<RefCell<Species>>], env: &Env)
+    {
+        let babies_stolen = env.babies_stolen as usize;
+        let mut stolen_babies: usize = 0;
+
+        //Take away a constant number of expected offspring from the worst few species
+        for species in sorted_species.iter().skip(1).rev() {
+            if stolen_babies >= babies_stolen {
+                break;
+            }
+
+            let mut species = species.borrow_mut();
+            if species.age > 5 && species.expected_offspring > 2 {
+                //This species has enough to finish off the stolen pool
+                if species.expected_offspring > babies_stolen - stolen_babies {
+                    species.expected_offspring -= babies_stolen - stolen_babies;
+                    stolen_babies = babies_stolen;
+                } else {
+                    //Not enough here to complete the pool of stolen
+                    stolen_babies += species.expected_offspring - 1;
+                    species.expected_offspring = 1;
+                }
+            }
+        }
+
+        //Gives some of the stolen babies to the champion of a species
+        let give = |species: &Rc<RefCell<Species>>, babies: usize| {
+            let mut species = species.borrow_mut();
+            species.expected_offspring += babies;
+            let mut champion = species.organisms[0].borrow_mut();
+            let super_champ_offspring = champion.get_super_champ_offspring();
+            champion.set_super_champ_offspring(super_champ_offspring + babies as i32);
+        };
+
+        //Don't give to dying species even if they are champs
+        let mut improving = sorted_species.iter()
+            .filter(|species| species.borrow().last_improved() <= env.dropoff_age);
+
+        //Concentrate A LOT on the number one species, less on the number two and three
+        for &share in &[babies_stolen / 5, babies_stolen / 5, babies_stolen / 10] {
+            let species = match improving.next() {
+                Some(species) => species,
+                None => break,
+            };
+            if stolen_babies >= share {
+                give(species, share);
+                stolen_babies -= share;
+            }
+        }
+
+        //Randomize a little which species get boosted by a super champ
+        for species in improving {
+            if stolen_babies == 0 {
+                break;
+            }
+            if rand::random::<f64>() > 0.1 {
+                let share = stolen_babies.min(3);
+                give(species, share);
+                stolen_babies -= share;
+            }
+        }
+
+        //If any stolen babies aren't taken, give them to species #1's champ
+        if stolen_babies > 0 {
+            give(&sorted_species[0], stolen_babies);
+        }
     }
 }
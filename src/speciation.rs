@@ -0,0 +1,379 @@
+use std::rc::Rc;
+use std::cell::RefCell;
+use std::collections::HashMap;
+use organism::Organism;
+use species::Species;
+use env::Env;
+
+/// ---------------------------------------------
+/// SPECIATION TRAIT:
+///   How the organisms of a generation are
+///   divided into species. The species of the
+///   previous generation are passed in, so that
+///   the ones that live on keep their ids and ages
+/// ---------------------------------------------
+pub trait Speciation {
+    /// Places every organism in a species, setting the species of the organism too.
+    /// The existing species still hold the previous generation. New species are
+    /// numbered after last_species, and the species left empty are dropped
+    fn speciate(&mut self, organisms: &[Rc<RefCell<Organism>>], species: &mut Vec<Rc<RefCell<Species>>>,
+                last_species: &mut usize, env: &Env);
+
+    /// The compatibility threshold, for the strategies that use one
+    fn compat_threshold(&self) -> Option<f64> { None }
+}
+
+/// Places an organism in a species
+fn join_species(organism: &Rc<RefCell<Organism>>, species: &Rc<RefCell<Species>>)
+{
+    species.borrow_mut().add_organism(organism.clone());
+    organism.borrow_mut().set_species(species.clone());
+}
+
+/// Places each organism in the first species whose representative is closer than
+/// the threshold, or in a new species if there is none. The existing species must
+/// have chosen their representative already, and be emptied of their previous generation.
+/// The distance is given from the index of an organism to the representative of
+/// a species, and is None when the species has no representative
+fn speciate_by_representative<F>(organisms: &[Rc<RefCell<Organism>>], species: &mut Vec<Rc<RefCell<Species>>>,
+                                 last_species: &mut usize, threshold: f64, mut distance: F)
+    where F: FnMut(usize, &Species) -> Option<f64>
+{
+    for (position, organism) in organisms.iter().enumerate() {
+        let mut compatible_species = None;
+        for current_species in species.iter() {
+            match distance(position, &current_species.borrow()) {
+                Some(distance) if distance < threshold => {
+                    compatible_species = Some(current_species.clone());
+                    break;
+                }
+                _ => continue,
+            }
+        }
+
+        let compatible_species = match compatible_species {
+            Some(compatible_species) => compatible_species,
+            None => {
+                *last_species += 1;
+                let new_species = Rc::new(RefCell::new(Species::new(*last_species)));
+                species.push(new_species.clone());
+                new_species
+            }
+        };
+        join_species(organism, &compatible_species);
+    }
+
+    species.retain(|species| !species.borrow().organisms.is_empty());
+}
+
+/// ---------------------------------------------
+/// THRESHOLD SPECIATION STRUCT:
+///   The NEAT speciation, and the default one.
+///   An organism joins the first species whose
+///   representative is compatible with it
+/// ---------------------------------------------
+#[derive(Debug, Clone)]
+pub struct ThresholdSpeciation {
+    /// The compatibility threshold in use, which is adjusted every generation
+    /// when the Env sets a target_species
+    compat_threshold: f64,
+}
+
+impl ThresholdSpeciation {
+    pub fn new(compat_threshold: f64) -> Self
+    {
+        ThresholdSpeciation { compat_threshold }
+    }
+
+    /// Moves the compatibility threshold by compat_threshold_step to steer the number
+    /// of species towards target_species, without going below compat_threshold_min.
+    /// Does nothing when target_species is 0
+    fn adjust_compat_threshold(&mut self, species_n: usize, env: &Env)
+    {
+        if env.target_species == 0 {
+            return;
+        }
+
+        //Fewer species need a stricter threshold, more species a looser one
+        if species_n < env.target_species {
+            self.compat_threshold -= env.compat_threshold_step;
+        } else if species_n > env.target_species {
+            self.compat_threshold += env.compat_threshold_step;
+        }
+
+        if self.compat_threshold < env.compat_threshold_min {
+            self.compat_threshold = env.compat_threshold_min;
+        }
+    }
+}
+
+impl Speciation for ThresholdSpeciation {
+    fn speciate(&mut self, organisms: &[Rc<RefCell<Organism>>], species: &mut Vec<Rc<RefCell<Species>>>,
+                last_species: &mut usize, env: &Env)
+    {
+        //The first generation has no species count to steer from
+        if !species.is_empty() {
+            self.adjust_compat_threshold(species.len(), env);
+        }
+
+        for current_species in species.iter() {
+            current_species.borrow_mut().choose_representative();
+        }
+        speciate_by_representative(organisms, species, last_species, self.compat_threshold, |position, species| {
+            species.representative()
+                .map(|representative| organisms[position].borrow().genome.compatibility(representative, env))
+        });
+    }
+
+    fn compat_threshold(&self) -> Option<f64> { Some(self.compat_threshold) }
+}
+
+/// The index of a medoid organism, with the species its cluster continues if any
+type Medoid = (usize, Option<Rc<RefCell<Species>>>);
+
+/// ---------------------------------------------
+/// K-MEDOIDS SPECIATION STRUCT:
+///   Clusters the organisms into a fixed number
+///   of species by genome compatibility. Each
+///   species is centered on its medoid, the
+///   member closest to all the others
+/// ---------------------------------------------
+#[derive(Debug, Clone)]
+pub struct KMedoidsSpeciation {
+    /// The number of species, fewer when there are fewer organisms.
+    /// A count of 0 is taken as 1, so that every organism has a species
+    pub species_count: usize,
+    /// How many times the medoids are moved at most
+    pub max_iterations: usize,
+}
+
+impl KMedoidsSpeciation {
+    pub fn new(species_count: usize) -> Self
+    {
+        KMedoidsSpeciation {
+            species_count,
+            max_iterations: 20,
+        }
+    }
+
+    /// Picks the starting medoids. The existing species, in order, start from
+    /// the organism closest to their representative, and the remaining medoids are
+    /// the organisms farthest from the ones already picked
+    fn initial_medoids(&self, organisms: &[Rc<RefCell<Organism>>], species: &[Rc<RefCell<Species>>],
+                       distances: &[Vec<f64>], env: &Env) -> Vec<Medoid>
+    {
+        let k = self.species_count.max(1).min(organisms.len());
+        let mut medoids: Vec<Medoid> = Vec::with_capacity(k);
+
+        for current_species in species {
+            if medoids.len() == k {
+                break;
+            }
+            let current = current_species.borrow();
+            let representative = match current.representative() {
+                Some(representative) => representative,
+                None => continue,
+            };
+
+            let closest = (0..organisms.len())
+                .filter(|&i| medoids.iter().all(|&(medoid, _)| medoid != i))
+                .map(|i| (i, organisms[i].borrow().genome.compatibility(representative, env)))
+                .fold(None, |closest: Option<(usize, f64)>, (i, distance)| match closest {
+                    Some((_, best)) if best <= distance => closest,
+                    _ => Some((i, distance)),
+                });
+            if let Some((i, _)) = closest {
+                medoids.push((i, Some(current_species.clone())));
+            }
+        }
+
+        while medoids.len() < k {
+            let farthest = (0..organisms.len())
+                .filter(|&i| medoids.iter().all(|&(medoid, _)| medoid != i))
+                .map(|i| (i, medoids.iter().map(|&(medoid, _)| distances[i][medoid]).fold(f64::INFINITY, f64::min)))
+                .fold(None, |farthest: Option<(usize, f64)>, (i, distance)| match farthest {
+                    Some((_, best)) if best >= distance => farthest,
+                    _ => Some((i, distance)),
+                });
+            match farthest {
+                Some((i, _)) => medoids.push((i, None)),
+                None => break,
+            }
+        }
+
+        medoids
+    }
+
+    /// The index of the closest medoid to each organism
+    fn assign(medoids: &[Medoid], distances: &[Vec<f64>]) -> Vec<usize>
+    {
+        let mut clusters: Vec<usize> = distances.iter()
+            .map(|row| {
+                let mut closest = 0;
+                for (cluster, &(medoid, _)) in medoids.iter().enumerate() {
+                    if row[medoid] < row[medoids[closest].0] {
+                        closest = cluster;
+                    }
+                }
+                closest
+            })
+            .collect();
+
+        //A medoid stays in its own cluster, even when tied with another one
+        for (cluster, &(medoid, _)) in medoids.iter().enumerate() {
+            clusters[medoid] = cluster;
+        }
+
+        clusters
+    }
+}
+
+impl Speciation for KMedoidsSpeciation {
+    fn speciate(&mut self, organisms: &[Rc<RefCell<Organism>>], species: &mut Vec<Rc<RefCell<Species>>>,
+                last_species: &mut usize, env: &Env)
+    {
+        for current_species in species.iter() {
+            current_species.borrow_mut().choose_representative();
+        }
+
+        let mut distances = vec![vec![0.0; organisms.len()]; organisms.len()];
+        for i in 0..organisms.len() {
+            for j in (i + 1)..organisms.len() {
+                let distance = organisms[i].borrow().genome.compatibility(&organisms[j].borrow().genome, env);
+                distances[i][j] = distance;
+                distances[j][i] = distance;
+            }
+        }
+
+        let mut medoids = self.initial_medoids(organisms, species, &distances, env);
+        if medoids.is_empty() {
+            species.clear();
+            return;
+        }
+
+        //Alternate between assigning the organisms and moving each medoid
+        //to the member with the smallest total distance to its cluster
+        let mut clusters = KMedoidsSpeciation::assign(&medoids, &distances);
+        for _ in 0..self.max_iterations {
+            let mut moved = false;
+            for (cluster, medoid) in medoids.iter_mut().enumerate() {
+                let members: Vec<usize> = (0..organisms.len()).filter(|&i| clusters[i] == cluster).collect();
+                let cost = |candidate: usize| members.iter().map(|&i| distances[candidate][i]).sum::<f64>();
+                let mut best = medoid.0;
+                for &candidate in &members {
+                    if cost(candidate) < cost(best) {
+                        best = candidate;
+                    }
+                }
+                if best != medoid.0 {
+                    medoid.0 = best;
+                    moved = true;
+                }
+            }
+            if !moved {
+                break;
+            }
+            clusters = KMedoidsSpeciation::assign(&medoids, &distances);
+        }
+
+        //The clusters that started from a species continue it, the others found new ones
+        let mut new_species = Vec::with_capacity(medoids.len());
+        for (medoid, previous_species) in medoids {
+            let cluster_species = match previous_species {
+                Some(previous_species) => previous_species,
+                None => {
+                    *last_species += 1;
+                    Rc::new(RefCell::new(Species::new(*last_species)))
+                }
+            };
+            //The medoid goes first, becoming the representative of a new species
+            join_species(&organisms[medoid], &cluster_species);
+            new_species.push((medoid, cluster_species));
+        }
+        for (i, organism) in organisms.iter().enumerate() {
+            let (medoid, ref cluster_species) = new_species[clusters[i]];
+            if i != medoid {
+                join_species(organism, cluster_species);
+            }
+        }
+
+        *species = new_species.into_iter().map(|(_, cluster_species)| cluster_species).collect();
+    }
+}
+
+/// Describes the behavior of an organism as a vector of values
+pub type BehaviorDescriptor = dyn FnMut(&mut Organism) -> Vec<f64>;
+
+/// ---------------------------------------------
+/// BEHAVIORAL SPECIATION STRUCT:
+///   Speciation by what the organisms do rather
+///   than by their genes. A user supplied function
+///   describes the behavior of an organism, and
+///   organisms join the first species whose
+///   representative behaves closer than the
+///   threshold (in euclidean distance)
+/// ---------------------------------------------
+pub struct BehavioralSpeciation {
+    pub threshold: f64,
+    /// Describes the behavior of an organism, e.g. its outputs on a set of inputs
+    descriptor: Box<BehaviorDescriptor>,
+}
+
+impl BehavioralSpeciation {
+    pub fn new<F>(threshold: f64, descriptor: F) -> Self
+        where F: FnMut(&mut Organism) -> Vec<f64> + 'static
+    {
+        BehavioralSpeciation {
+            threshold,
+            descriptor: Box::new(descriptor),
+        }
+    }
+
+    /// The euclidean distance between two behaviors, the missing values counting as 0
+    pub fn behavioral_distance(behavior1: &[f64], behavior2: &[f64]) -> f64
+    {
+        let len = behavior1.len().max(behavior2.len());
+        (0..len)
+            .map(|i| behavior1.get(i).unwrap_or(&0.0) - behavior2.get(i).unwrap_or(&0.0))
+            .map(|difference| difference * difference)
+            .sum::<f64>()
+            .sqrt()
+    }
+}
+
+/// The position of an organism in the generation
+fn position_of(organisms: &[Rc<RefCell<Organism>>], organism: &Rc<RefCell<Organism>>) -> Option<usize>
+{
+    organisms.iter().position(|other| Rc::ptr_eq(other, organism))
+}
+
+impl Speciation for BehavioralSpeciation {
+    fn speciate(&mut self, organisms: &[Rc<RefCell<Organism>>], species: &mut Vec<Rc<RefCell<Species>>>,
+                last_species: &mut usize, _env: &Env)
+    {
+        //The existing species are represented by the behavior of the same organism
+        //whose genome they keep as representative
+        let descriptor = &mut self.descriptor;
+        let mut representatives: HashMap<usize, Vec<f64>> = HashMap::new();
+        for current_species in species.iter() {
+            let mut current_species = current_species.borrow_mut();
+            if let Some(representative) = current_species.choose_representative() {
+                representatives.insert(current_species.get_id(), descriptor(&mut representative.borrow_mut()));
+            }
+        }
+
+        //Each organism is described only once, however many species it is compared with
+        let behaviors: Vec<Vec<f64>> = organisms.iter()
+            .map(|organism| descriptor(&mut organism.borrow_mut()))
+            .collect();
+
+        //The species founded in this generation are represented by their founder
+        speciate_by_representative(organisms, species, last_species, self.threshold, |position, species| {
+            let representative = match representatives.get(&species.get_id()) {
+                Some(representative) => representative,
+                None => &behaviors[position_of(organisms, species.organisms.first()?)?],
+            };
+            Some(BehavioralSpeciation::behavioral_distance(&behaviors[position], representative))
+        });
+    }
+}
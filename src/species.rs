@@ -1,11 +1,20 @@
 use std::rc::Rc;
 use std::cell::RefCell;
 use organism::Organism;
+use genome::Genome;
+use innovation::InnovationTable;
 use env::Env;
+use Mutator;
+use rand;
+use rand::Rng;
 
 pub struct Species {
     id: usize,
     pub organisms: Vec<Rc<RefCell<Organism>>>,
+    /// A copy of the genome new members are compared with. It is copied rather than
+    /// shared so that the organisms of past generations, which point back at their
+    /// species, do not keep the species alive
+    representative: Option<Genome>,
     pub average_fitness: f64,
     pub max_fitness: f64,
     pub max_fitness_ever: f64,
@@ -21,6 +30,7 @@ impl Species {
         Species {
             id,
             organisms: Vec::new(),
+            representative: None,
             average_fitness: 0.0,
             max_fitness: 0.0,
             max_fitness_ever: 0.0,
@@ -31,11 +41,34 @@ impl Species {
         }
     }
 
+    pub fn get_id(&self) -> usize { self.id }
+
+    /// Adds an organism, whose genome represents a species without a representative
     pub fn add_organism(&mut self, organism: Rc<RefCell<Organism>>)
     {
+        if self.representative.is_none() {
+            let organism = organism.borrow();
+            self.representative = Some(Genome::clone(&organism.genome, organism.genome.id));
+        }
         self.organisms.push(organism)
     }
 
+    pub fn representative(&self) -> Option<&Genome> { self.representative.as_ref() }
+
+    /// Picks the genome of a random organism as the representative, then empties the species
+    /// so that the next generation can be placed in it. Returns the chosen organism
+    pub fn choose_representative(&mut self) -> Option<Rc<RefCell<Organism>>>
+    {
+        let chosen = rand::thread_rng().choose(&self.organisms).cloned();
+        if let Some(ref organism) = chosen {
+            let organism = organism.borrow();
+            self.representative = Some(Genome::clone(&organism.genome, organism.genome.id));
+        }
+        self.organisms.clear();
+
+        chosen
+    }
+
     pub fn compute_max_and_average_fitness(&mut self)
                                            -> (f64, f64)
     {
@@ -102,7 +135,7 @@ impl Species {
         //Sort the population and mark for death those after survival_thresh*pop_size
         //organisms.qsort(order_orgs);
         self.organisms.sort_by(
-            |a, b| a.borrow().fitness.partial_cmp(&b.borrow().fitness).unwrap()
+            |a, b| b.borrow().fitness.partial_cmp(&a.borrow().fitness).unwrap()
         );
 
 
@@ -152,4 +185,123 @@ impl Species {
 
         skim
     }
-}
\ No newline at end of file
+
+    /// Removes the organisms marked for death
+    pub fn remove_eliminated(&mut self)
+    {
+        self.organisms.retain(|organism| !organism.borrow().is_eliminated());
+    }
+
+    /// Number of generations since the species last improved
+    pub fn last_improved(&self) -> usize { self.age - self.age_of_last_improvement }
+
+    /// Makes the expected_offspring babies of the species from its surviving organisms.
+    /// The champion, which comes first, first makes its super champion offspring,
+    /// which are clones with mutated weights or a new link except for the last one,
+    /// an exact clone. The champion of a species with more than five members
+    /// is also copied unchanged. The other babies come either from mutating a clone
+    /// of a parent with probability mutate_only_prob, or from mating two parents.
+    /// The second parent comes from another species in sorted_species with
+    /// probability interspecies_mate_rate. Babies of mating are also mutated,
+    /// unless mate_only_prob says otherwise.
+    /// The species must not be mutably borrowed, as it is in sorted_species.
+    /// The genomes of the babies still need their ids
+    pub fn reproduce(&self, generation: usize, env: &Env, sorted_species: &[Rc<RefCell<Species>>],
+                     innovations: &mut InnovationTable, cur_node_id: &mut i32, cur_innov_num: &mut f64)
+                     -> Vec<Organism>
+    {
+        let mut babies = Vec::with_capacity(self.expected_offspring);
+        let parents: Vec<&Rc<RefCell<Organism>>> = self.organisms.iter()
+            .filter(|organism| !organism.borrow().is_eliminated())
+            .collect();
+        if parents.is_empty() {
+            return babies;
+        }
+
+        let mut rng = rand::thread_rng();
+        let pool_size = parents.len();
+        let champion = parents[0];
+        let mut champ_done = false;
+
+        for _ in 0..self.expected_offspring {
+            let super_champ_offspring = champion.borrow().get_super_champ_offspring();
+
+            //If we have a super_champ (Population champion), finish off some special clones
+            if super_champ_offspring > 0 {
+                let mut new_genome = champion.borrow().genome.clone(0);
+
+                //Most superchamp offspring will have their connection weights mutated only
+                //The last offspring will be an exact duplicate of this super_champ
+                if super_champ_offspring > 1 {
+                    //Make sure no links get added when the system has link adding disabled
+                    if rng.gen::<f64>() < 0.8 || env.mutate_add_link_prob == 0.0 {
+                        new_genome.mutate_link_weights(env.weight_mut_power, 1.0, Mutator::Gaussian);
+                    } else {
+                        //Sometimes we add a link to a superchamp
+                        new_genome.mutate_add_link(env, innovations, cur_innov_num);
+                    }
+                }
+
+                let mut baby = Organism::new(0.0, Box::new(new_genome), generation);
+                let mut champion = champion.borrow_mut();
+                if super_champ_offspring == 1 && champion.is_pop_champ() {
+                    baby.set_pop_champ_child(champion.orig_fitness);
+                }
+                champion.set_super_champ_offspring(super_champ_offspring - 1);
+
+                babies.push(baby);
+                continue;
+            }
+
+            //If we have a Species champion, just clone it
+            if !champ_done && self.organisms.len() > 5 {
+                let new_genome = champion.borrow().genome.clone(0);
+                babies.push(Organism::new(0.0, Box::new(new_genome), generation));
+                champ_done = true;
+                continue;
+            }
+
+            let mom = parents[rng.gen_range(0, pool_size)].borrow();
+
+            //Mutate only, or when there is nobody to mate with
+            if rng.gen::<f64>() < env.mutate_only_prob || pool_size == 1 {
+                let mut new_genome = mom.genome.clone(0);
+                new_genome.mutate(env, innovations, cur_node_id, cur_innov_num);
+                babies.push(Organism::new(0.0, Box::new(new_genome), generation));
+                continue;
+            }
+
+            //Choose the dad, from the species or from outside of it
+            let dad = if rng.gen::<f64>() > env.interspecies_mate_rate {
+                parents[rng.gen_range(0, pool_size)].clone()
+            } else {
+                //Try to find another species, the dad will be its champion
+                let mut dad = parents[rng.gen_range(0, pool_size)].clone();
+                for _ in 0..5 {
+                    let other = sorted_species[rng.gen_range(0, sorted_species.len())].borrow();
+                    if other.id != self.id {
+                        if let Some(champion) = other.organisms.first() {
+                            dad = champion.clone();
+                            break;
+                        }
+                    }
+                }
+                dad
+            };
+            let dad = dad.borrow();
+
+            let mut new_genome = mom.genome.mate(&dad.genome, mom.orig_fitness, dad.orig_fitness, 0, env);
+
+            //Mutate the baby, surely if the parents were the same or identical
+            if rng.gen::<f64>() > env.mate_only_prob
+                || dad.genome.id == mom.genome.id
+                || dad.genome.compatibility(&mom.genome, env) == 0.0 {
+                new_genome.mutate(env, innovations, cur_node_id, cur_innov_num);
+            }
+
+            babies.push(Organism::new(0.0, Box::new(new_genome), generation));
+        }
+
+        babies
+    }
+}
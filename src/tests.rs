@@ -1,18 +1,46 @@
 use env::Env;
 use population::Population;
 use genome::Genome;
+use genome::compatibility::Compatibility;
 use genome::gene::Gene;
 use genome::gene_trait::Trait;
 use node::Node;
-use node::{NodeType, NodePlace};
-use std::rc::Rc;
-use std::cell::RefCell;
+use node::{NodeType, NodePlace, FunctionType, AggregationType, MIN_TIME_CONSTANT};
+use std::rc::{Rc, Weak};
+use std::cell::{Cell, RefCell};
 use std::path::Path;
+use std::ops::Range;
 use organism::Organism;
+use species::Species;
+use network::{Network, Dynamics};
+use innovation::InnovationTable;
+use speciation::{KMedoidsSpeciation, BehavioralSpeciation};
+use std::thread;
 
 #[test]
 fn it_loads_env() {
     let env = Env::load_from_file("assets/test.ne", true).unwrap();
+    assert_eq!(env.dynamics_mut_power, 0.5);
+}
+
+/// Builds the XOR starting genome: a bias and two inputs fully connected to one output
+fn xor_start_genome() -> Genome {
+    let mut start_genome = Genome::new(1);
+
+    start_genome.add_trait(Trait::new(1, [0.1, 0.0, 0.0, 0.0, 0.0, 0.0, 0.0, 0.0]));
+    start_genome.add_trait(Trait::new(2, [0.2, 0.0, 0.0, 0.0, 0.0, 0.0, 0.0, 0.0]));
+    start_genome.add_trait(Trait::new(3, [0.3, 0.0, 0.0, 0.0, 0.0, 0.0, 0.0, 0.0]));
+
+    start_genome.add_node(Node::new(1, None, NodeType::Sensor, NodePlace::Bias));
+    start_genome.add_node(Node::new(2, None, NodeType::Sensor, NodePlace::Input));
+    start_genome.add_node(Node::new(3, None, NodeType::Sensor, NodePlace::Input));
+    start_genome.add_node(Node::new(4, None, NodeType::Neuron, NodePlace::Output));
+
+    start_genome.add_gene(Gene::new(Some(1), 1, 4, 0.0, false, 1.0, 0.0, true));
+    start_genome.add_gene(Gene::new(Some(2), 2, 4, 0.0, false, 2.0, 0.0, true));
+    start_genome.add_gene(Gene::new(Some(3), 3, 4, 0.0, false, 3.0, 0.0, true));
+
+    start_genome
 }
 
 #[test]
@@ -39,27 +67,7 @@ fn xor_test() {
     let mut exp_count: u32;
     let mut samples: u32;
 
-    let mut start_genome = Genome::new(1);
-
-    let trait_1 = Rc::new(RefCell::new(Trait::new(1, [0.1, 0.0, 0.0, 0.0, 0.0, 0.0, 0.0, 0.0])));
-    let trait_2 = Rc::new(RefCell::new(Trait::new(2, [0.2, 0.0, 0.0, 0.0, 0.0, 0.0, 0.0, 0.0])));
-    let trait_3 = Rc::new(RefCell::new(Trait::new(3, [0.3, 0.0, 0.0, 0.0, 0.0, 0.0, 0.0, 0.0])));
-    start_genome.add_trait(trait_1.clone());
-    start_genome.add_trait(trait_2.clone());
-    start_genome.add_trait(trait_3.clone());
-
-    let node_1 = Rc::new(RefCell::new(Node::new(1, None, NodeType::Sensor, NodePlace::Bias)));
-    let node_2 = Rc::new(RefCell::new(Node::new(2, None, NodeType::Sensor, NodePlace::Input)));
-    let node_3 = Rc::new(RefCell::new(Node::new(3, None, NodeType::Sensor, NodePlace::Input)));
-    let node_4 = Rc::new(RefCell::new(Node::new(4, None, NodeType::Neuron, NodePlace::Output)));
-    start_genome.add_node(node_1.clone());
-    start_genome.add_node(node_2.clone());
-    start_genome.add_node(node_3.clone());
-    start_genome.add_node(node_4.clone());
-
-    start_genome.add_gene(Gene::new(Some(trait_1), node_1, node_4.clone(), 0.0, false, 1.0, 0.0, true));
-    start_genome.add_gene(Gene::new(Some(trait_2), node_2, node_4.clone(), 0.0, false, 2.0, 0.0, true));
-    start_genome.add_gene(Gene::new(Some(trait_3), node_3, node_4.clone(), 0.0, false, 3.0, 0.0, true));
+    let start_genome = xor_start_genome();
 
     for exp_count in 0..env.num_runs {
         println!("Spawning Population off Genome2");
@@ -162,3 +170,990 @@ fn test_rand() {
 
     println!("Min {} Max {} Average {}", min, max, average);
 }
+
+#[test]
+fn genesis_decodes_network() {
+    let mut genome = xor_start_genome();
+    // A disabled gene must not be expressed in the phenotype
+    genome.add_gene(Gene::new(None, 2, 4, 1.0, false, 4.0, 0.0, false));
+
+    let network = genome.genesis(genome.id);
+
+    assert_eq!(network.net_id, 1);
+    assert_eq!(network.nodes_n(), 4);
+    assert_eq!(network.biases_n(), 1);
+    assert_eq!(network.inputs_n(), 2);
+    assert_eq!(network.outputs_n(), 1);
+    assert_eq!(network.links_n(), 3);
+}
+
+/// Builds a genome with a bias and two inputs connected to one output with the given weights
+fn weighted_genome(weights: [f64; 3]) -> Genome {
+    let mut genome = Genome::new(1);
+
+    genome.add_node(Node::new(1, None, NodeType::Sensor, NodePlace::Bias));
+    genome.add_node(Node::new(2, None, NodeType::Sensor, NodePlace::Input));
+    genome.add_node(Node::new(3, None, NodeType::Sensor, NodePlace::Input));
+    genome.add_node(Node::new(4, None, NodeType::Neuron, NodePlace::Output));
+
+    genome.add_gene(Gene::new(None, 1, 4, weights[0], false, 1.0, 0.0, true));
+    genome.add_gene(Gene::new(None, 2, 4, weights[1], false, 2.0, 0.0, true));
+    genome.add_gene(Gene::new(None, 3, 4, weights[2], false, 3.0, 0.0, true));
+
+    genome
+}
+
+/// Runs the epochs of the given generations, giving every organism
+/// the fitness computed by fitness_fn before each one
+fn run_epochs<F>(population: &mut Population, env: &Env, generations: Range<usize>, fitness_fn: F)
+    where F: Fn(&Organism) -> f64
+{
+    for generation in generations {
+        for organism in &population.organisms {
+            let fitness = fitness_fn(&organism.borrow());
+            organism.borrow_mut().fitness = fitness;
+        }
+        for species in &population.species {
+            species.borrow_mut().compute_max_and_average_fitness();
+        }
+
+        population.epoch(generation, env);
+    }
+}
+
+#[test]
+fn network_activates() {
+    let mut network = weighted_genome([-0.5, 1.0, 2.0]).genesis(1);
+
+    assert!(network.load_sensors(&[1.0]).is_err());
+    network.load_sensors(&[1.0, 0.0]).unwrap();
+    network.activate().unwrap();
+
+    let outputs = network.outputs();
+    let expected = 1.0 / (1.0 + (-4.924273 * 0.5f64).exp());
+    assert_eq!(outputs.len(), 1);
+    assert!((outputs[0] - expected).abs() < 1e-12);
+}
+
+#[test]
+fn network_reports_unreachable_output() {
+    let mut genome = Genome::new(1);
+    genome.add_node(Node::new(1, None, NodeType::Sensor, NodePlace::Input));
+    genome.add_node(Node::new(2, None, NodeType::Neuron, NodePlace::Output));
+
+    let mut network = genome.genesis(1);
+    network.load_sensors(&[1.0]).unwrap();
+    assert!(network.activate().is_err());
+}
+
+#[test]
+fn network_time_delayed_link_and_flush() {
+    let mut genome = Genome::new(1);
+    genome.add_node(Node::new(1, None, NodeType::Sensor, NodePlace::Bias));
+    genome.add_node(Node::new(2, None, NodeType::Sensor, NodePlace::Input));
+    genome.add_node(Node::new(3, None, NodeType::Neuron, NodePlace::Output));
+
+    genome.add_gene(Gene::new(None, 1, 3, 0.0, false, 1.0, 0.0, true));
+    let mut delayed = Gene::new(None, 2, 3, 1.0, true, 2.0, 0.0, true);
+    delayed.link.set_time_delay(true);
+    genome.add_gene(delayed);
+
+    let mut network = genome.genesis(1);
+    let sigmoid = |x: f64| 1.0 / (1.0 + (-4.924273 * x).exp());
+
+    // The delayed signal is not there yet
+    network.load_sensors(&[1.0]).unwrap();
+    network.activate().unwrap();
+    assert!((network.outputs()[0] - sigmoid(0.0)).abs() < 1e-12);
+
+    // Now the output sees the input from the previous step
+    network.load_sensors(&[0.0]).unwrap();
+    network.activate().unwrap();
+    assert!((network.outputs()[0] - sigmoid(1.0)).abs() < 1e-12);
+
+    // After a flush the past is forgotten
+    network.flush();
+    assert!(network.outputs_off());
+    network.load_sensors(&[0.0]).unwrap();
+    network.activate().unwrap();
+    assert!((network.outputs()[0] - sigmoid(0.0)).abs() < 1e-12);
+}
+
+#[test]
+fn compiled_network_matches_network() {
+    let genome = weighted_genome([-0.5, 1.0, 2.0]);
+    let mut network = genome.genesis(1);
+    let compiled = network.compile().unwrap();
+
+    assert_eq!(compiled.inputs_n(), 2);
+    assert_eq!(compiled.outputs_n(), 1);
+
+    for inputs in &[[0.0, 0.0], [0.0, 1.0], [1.0, 0.0], [1.0, 1.0]] {
+        network.flush();
+        network.load_sensors(inputs).unwrap();
+        network.activate().unwrap();
+        assert!((network.outputs()[0] - compiled.evaluate(inputs)[0]).abs() < 1e-12);
+    }
+}
+
+#[test]
+fn compiled_network_matches_relaxed_network() {
+    let mut genome = Genome::new(1);
+    genome.add_node(Node::new(1, None, NodeType::Sensor, NodePlace::Input));
+    genome.add_node(Node::new(2, None, NodeType::Neuron, NodePlace::Hidden));
+    genome.add_node(Node::new(3, None, NodeType::Neuron, NodePlace::Output));
+    genome.add_gene(Gene::new(None, 1, 3, 1.0, false, 1.0, 0.0, true));
+    genome.add_gene(Gene::new(None, 1, 2, 1.0, false, 2.0, 0.0, true));
+    genome.add_gene(Gene::new(None, 2, 3, 1.0, false, 3.0, 0.0, true));
+
+    let mut network = genome.genesis(1);
+    let compiled = network.compile().unwrap();
+
+    for inputs in &[[0.0], [0.5], [1.0]] {
+        network.flush();
+        network.load_sensors(inputs).unwrap();
+
+        // The first activation does not see the hidden node yet,
+        // the network is settled once it has been activated as many times as it is deep
+        network.activate().unwrap();
+        let first = network.outputs()[0];
+        network.activate().unwrap();
+        assert!((first - network.outputs()[0]).abs() > 1e-6);
+        assert!((network.outputs()[0] - compiled.evaluate(inputs)[0]).abs() < 1e-12);
+
+        network.activate().unwrap();
+        assert!((network.outputs()[0] - compiled.evaluate(inputs)[0]).abs() < 1e-12);
+    }
+}
+
+#[test]
+fn compiled_network_rejects_recurrent() {
+    let mut genome = Genome::new(1);
+    genome.add_node(Node::new(1, None, NodeType::Sensor, NodePlace::Input));
+    genome.add_node(Node::new(2, None, NodeType::Neuron, NodePlace::Output));
+    genome.add_gene(Gene::new(None, 1, 2, 1.0, false, 1.0, 0.0, true));
+    genome.add_gene(Gene::new(None, 2, 2, 1.0, true, 2.0, 0.0, true));
+
+    assert!(genome.genesis(1).compile().is_err());
+}
+
+#[test]
+fn compiled_network_evaluates_batch() {
+    let compiled = weighted_genome([-0.5, 1.0, 2.0]).genesis(1).compile().unwrap();
+    let rows = vec![vec![0.0, 0.0], vec![0.0, 1.0], vec![1.0, 0.0], vec![1.0, 1.0]];
+
+    let outputs = compiled.evaluate_batch(&rows);
+
+    assert_eq!(outputs.len(), rows.len());
+    for (row, output) in rows.iter().zip(&outputs) {
+        assert_eq!(output, &compiled.evaluate(row));
+    }
+    assert!(compiled.evaluate_batch::<Vec<f64>>(&[]).is_empty());
+}
+
+#[test]
+fn network_dispatches_function_type() {
+    let mut genome = Genome::new(1);
+    let mut output = Node::new(3, None, NodeType::Neuron, NodePlace::Output);
+    output.set_function_type(FunctionType::Identity);
+    genome.add_node(Node::new(1, None, NodeType::Sensor, NodePlace::Input));
+    genome.add_node(Node::new(2, None, NodeType::Neuron, NodePlace::Hidden));
+    genome.add_node(output);
+    genome.add_gene(Gene::new(None, 1, 3, -3.0, false, 1.0, 0.0, true));
+
+    let mut network = genome.genesis(1);
+    network.load_sensors(&[1.5]).unwrap();
+    network.activate().unwrap();
+    assert_eq!(network.outputs(), vec![-4.5]);
+    assert_eq!(network.compile().unwrap().evaluate(&[1.5]), vec![-4.5]);
+
+    // The only hidden node gets a different function
+    assert!(genome.mutate_function_type());
+    assert!(genome.get_node(2).unwrap().function_type() != FunctionType::Sigmoid);
+
+    assert_eq!(FunctionType::Relu.apply(-2.0), 0.0);
+    assert_eq!(FunctionType::Step.apply(0.5), 1.0);
+    assert_eq!(FunctionType::Clamped.apply(3.0), 1.0);
+    assert_eq!(FunctionType::Gaussian.apply(0.0), 1.0);
+}
+
+#[test]
+fn network_dispatches_aggregation() {
+    let mut genome = Genome::new(1);
+    let mut output = Node::new(3, None, NodeType::Neuron, NodePlace::Output);
+    output.set_function_type(FunctionType::Identity);
+    output.set_aggregation(AggregationType::Product);
+    genome.add_trait(Trait::new(1, [0.0; 8]));
+    genome.add_node(Node::new(1, None, NodeType::Sensor, NodePlace::Input));
+    genome.add_node(Node::new(2, None, NodeType::Sensor, NodePlace::Input));
+    genome.add_node(output);
+    genome.add_gene(Gene::new(None, 1, 3, 2.0, false, 1.0, 0.0, true));
+    genome.add_gene(Gene::new(None, 2, 3, 1.0, false, 2.0, 0.0, true));
+
+    // The aggregation is carried by clones
+    let mut network = genome.clone(2).genesis(2);
+    network.load_sensors(&[1.5, 3.0]).unwrap();
+    network.activate().unwrap();
+    assert_eq!(network.outputs(), vec![9.0]);
+
+    let compiled = network.compile().unwrap();
+    assert_eq!(compiled.evaluate(&[1.5, 3.0]), vec![9.0]);
+    assert_eq!(compiled.evaluate_batch(&[[1.5, 3.0], [-1.0, 2.0]]), vec![vec![9.0], vec![-4.0]]);
+
+    assert_eq!(AggregationType::Median.apply(&[3.0, -1.0, 2.0]), 2.0);
+    assert_eq!(AggregationType::Median.apply(&[3.0, -1.0, 2.0, 0.0]), 1.0);
+    assert_eq!(AggregationType::Median.apply(&[f64::NAN, -1.0, 2.0]), 2.0);
+    assert_eq!(AggregationType::Max.apply(&[3.0, -1.0]), 3.0);
+    assert_eq!(AggregationType::Mean.apply(&[]), 0.0);
+}
+
+#[test]
+fn compiled_network_dead_hidden_node() {
+    // The hidden node has no incoming link, so it never activates
+    // but its link still sends a 0 signal to the output
+    for &aggregation in &AggregationType::ALL {
+        let mut genome = Genome::new(1);
+        let mut output = Node::new(3, None, NodeType::Neuron, NodePlace::Output);
+        output.set_function_type(FunctionType::Identity);
+        output.set_aggregation(aggregation);
+        genome.add_node(Node::new(1, None, NodeType::Sensor, NodePlace::Input));
+        genome.add_node(Node::new(2, None, NodeType::Neuron, NodePlace::Hidden));
+        genome.add_node(output);
+        genome.add_gene(Gene::new(None, 1, 3, 2.0, false, 1.0, 0.0, true));
+        genome.add_gene(Gene::new(None, 2, 3, 1.0, false, 2.0, 0.0, true));
+
+        let compiled = genome.genesis(1).compile().unwrap();
+        for inputs in &[[1.5], [-2.0]] {
+            let mut network = genome.genesis(1);
+            network.load_sensors(inputs).unwrap();
+            network.activate().unwrap();
+
+            assert_eq!(network.outputs(), compiled.evaluate(inputs), "{:?}", aggregation);
+            assert_eq!(vec![network.outputs()], compiled.evaluate_batch(&[inputs]), "{:?}", aggregation);
+        }
+    }
+}
+
+#[test]
+fn adaptive_network_learns() {
+    let mut genome = Genome::new(1);
+    genome.add_trait(Trait::new(1, [0.5, 0.0, 0.0, 0.0, 0.0, 0.0, 0.0, 0.0]));
+    genome.add_node(Node::new(1, None, NodeType::Sensor, NodePlace::Input));
+    genome.add_node(Node::new(2, None, NodeType::Neuron, NodePlace::Output));
+    genome.add_gene(Gene::new(Some(1), 1, 2, 1.0, false, 1.0, 0.0, true));
+
+    let run = |adaptable: bool| {
+        let mut network = genome.genesis(1);
+        network.set_adaptable(adaptable);
+        let mut outputs = Vec::new();
+        for _ in 0..2 {
+            network.load_sensors(&[1.0]).unwrap();
+            network.activate().unwrap();
+            outputs.push(network.outputs()[0]);
+        }
+        outputs
+    };
+
+    let fixed = run(false);
+    assert_eq!(fixed[0], fixed[1]);
+    let learned = run(true);
+    assert_eq!(learned[0], fixed[0]);
+    assert!(learned[1] > learned[0]);
+
+    use network::plasticity;
+    assert_eq!(plasticity::hebbian(1.0, 8.0, 1.0, 1.0, 0.0, 0.0), 1.0);
+    assert_eq!(plasticity::habituation(2.0, 1.0, 0.5), 1.0);
+    assert_eq!(plasticity::sensitization(-4.0, 8.0, 1.0, 0.5), -6.0);
+    // A rate of 0 leaves even a weight beyond the bound unchanged
+    assert_eq!(plasticity::hebbian(12.0, 8.0, 1.0, 1.0, 0.0, 0.0), 12.0);
+    // Both rules bound the weights the same way
+    assert_eq!(plasticity::weight_bound(2.0), 5.0);
+    assert_eq!(plasticity::hebbian(-9.0, 2.0, 0.0, 0.0, 0.1, 0.1), -5.0);
+    assert_eq!(plasticity::sensitization(-4.0, 2.0, 1.0, 0.5), -4.5);
+}
+
+#[test]
+fn ctrnn_network_integrates() {
+    let mut genome = Genome::new(1);
+    let mut output = Node::new(2, None, NodeType::Neuron, NodePlace::Output);
+    output.set_function_type(FunctionType::Identity);
+    output.set_time_constant(2.0);
+    output.set_bias(0.5);
+    genome.add_node(Node::new(1, None, NodeType::Sensor, NodePlace::Input));
+    genome.add_node(output);
+    genome.add_gene(Gene::new(None, 1, 2, 0.5, false, 1.0, 0.0, true));
+
+    let mut network = genome.genesis(1);
+    network.set_dynamics(Dynamics::Ctrnn { time_step: 0.5 });
+
+    network.load_sensors(&[1.0]).unwrap();
+    network.activate().unwrap();
+    assert_eq!(network.outputs(), vec![0.25]);
+    network.load_sensors(&[1.0]).unwrap();
+    network.activate().unwrap();
+    assert_eq!(network.outputs(), vec![0.4375]);
+
+    assert!(genome.mutate_node_dynamics(10.0));
+    assert!(genome.get_node(2).unwrap().get_time_constant() >= MIN_TIME_CONSTANT);
+}
+
+#[test]
+fn genome_is_send_and_sync() {
+    fn assert_send_sync<T: Send + Sync>() {}
+    assert_send_sync::<Genome>();
+    assert_send_sync::<Network>();
+
+    let genome = xor_start_genome();
+    let clone = thread::spawn(move || genome.clone(2)).join().unwrap();
+    assert_eq!(clone.id, 2);
+    clone.verify().unwrap();
+    assert_eq!(clone.genesis(2).links_n(), 3);
+}
+
+#[test]
+fn genome_mutate_add_node() {
+    let mut genome = weighted_genome([-0.5, 1.0, 2.0]);
+    let mut cur_node_id = genome.get_last_node_id().unwrap();
+    let mut cur_innov_num = genome.get_last_gene_innovnum().unwrap();
+    let mut innovations = InnovationTable::new();
+
+    assert!(genome.mutate_add_node(&mut innovations, &mut cur_node_id, &mut cur_innov_num));
+
+    assert_eq!(cur_node_id, 5);
+    assert_eq!(cur_innov_num, 5.0);
+    assert_eq!(genome.nodes_n(), 5);
+    assert_eq!(genome.get_node(5).unwrap().node_place(), NodePlace::Hidden);
+    // The split gene is disabled and replaced by two new ones
+    assert_eq!(genome.extrons(), 4);
+    assert_eq!(genome.get_last_gene_innovnum(), Some(5.0));
+    genome.verify().unwrap();
+
+    let mut network = genome.genesis(1);
+    assert_eq!(network.nodes_n(), 5);
+    assert_eq!(network.links_n(), 4);
+    network.load_sensors(&[1.0, 1.0]).unwrap();
+    network.activate().unwrap();
+}
+
+#[test]
+fn genome_mutate_add_link() {
+    let mut env = Env::new();
+    env.newlink_tries = 50;
+
+    let mut genome = xor_start_genome();
+    let mut cur_innov_num = genome.get_last_gene_innovnum().unwrap();
+    let mut innovations = InnovationTable::new();
+
+    // All the feed-forward links already exist
+    assert!(!genome.mutate_add_link(&env, &mut innovations, &mut cur_innov_num));
+    assert_eq!(cur_innov_num, 3.0);
+
+    // Forcing recurrence, only the loop on the output is left
+    env.recur_only_prob = 1.0;
+    assert!(genome.mutate_add_link(&env, &mut innovations, &mut cur_innov_num));
+    assert_eq!(cur_innov_num, 4.0);
+    assert_eq!(genome.extrons(), 4);
+    assert!(genome.is_recurrent_link(4, 4));
+    assert!(!genome.mutate_add_link(&env, &mut innovations, &mut cur_innov_num));
+
+    // A new hidden node can be reached from the inputs without recurrence,
+    // while linking the output back to it closes a loop
+    let mut cur_node_id = genome.get_last_node_id().unwrap();
+    assert!(genome.mutate_add_node(&mut innovations, &mut cur_node_id, &mut cur_innov_num));
+    assert!(!genome.is_recurrent_link(2, cur_node_id));
+    assert!(genome.is_recurrent_link(4, cur_node_id));
+
+    env.recur_only_prob = 0.0;
+    while genome.mutate_add_link(&env, &mut innovations, &mut cur_innov_num) {}
+    genome.verify().unwrap();
+    assert_eq!(genome.get_last_gene_innovnum(), Some(cur_innov_num));
+}
+
+#[test]
+fn genome_mutate_toggle_enable() {
+    // A single chain: each node has only one outgoing link
+    let mut genome = Genome::new(1);
+    genome.add_trait(Trait::new(1, [0.0; 8]));
+    genome.add_node(Node::new(1, Some(1), NodeType::Sensor, NodePlace::Input));
+    genome.add_node(Node::new(2, Some(1), NodeType::Neuron, NodePlace::Hidden));
+    genome.add_node(Node::new(3, Some(1), NodeType::Neuron, NodePlace::Output));
+    genome.add_gene(Gene::new(Some(1), 1, 2, 1.0, false, 1.0, 0.0, true));
+    genome.add_gene(Gene::new(Some(1), 2, 3, 1.0, false, 2.0, 0.0, true));
+
+    // No gene can be disabled without isolating part of the network
+    assert!(!genome.mutate_toggle_enable(20));
+    assert_eq!(genome.extrons(), 2);
+
+    // With a second way out of the input, the genes out of it can be disabled,
+    // but never both at once
+    genome.add_gene(Gene::new(Some(1), 1, 3, 1.0, false, 3.0, 0.0, true));
+    for _ in 0..20 {
+        genome.mutate_toggle_enable(1);
+        assert!(genome.extrons() >= 2);
+    }
+
+    while genome.mutate_gene_reenable() {}
+    assert_eq!(genome.extrons(), 3);
+    assert!(!genome.mutate_gene_reenable());
+}
+
+#[test]
+fn genome_mutate_traits() {
+    let mut gene_trait = Trait::new(1, [0.5; 8]);
+    gene_trait.mutate(10.0, 1.0);
+    assert!(gene_trait.params().iter().all(|param| *param >= 0.0 && *param <= 1.0));
+    let mut unchanged = Trait::new(1, [0.5; 8]);
+    unchanged.mutate(10.0, 0.0);
+    assert_eq!(unchanged.params(), &[0.5; 8]);
+
+    let mut genome = xor_start_genome();
+    assert!(genome.mutate_random_trait(0.5, 1.0));
+
+    // Pointing a link to another trait moves its mutation number
+    for _ in 0..50 {
+        genome.mutate_link_trait(1, 1.0);
+        genome.mutate_node_trait(1, 1.0);
+    }
+    assert!(genome.genes().iter().any(|gene| gene.mutation_num != 0.0));
+    genome.verify().unwrap();
+
+    // Without other traits to point to, nothing can change
+    let mut empty = Genome::new(1);
+    assert!(!empty.mutate_random_trait(0.5, 1.0));
+    assert!(!empty.mutate_link_trait(10, 1.0));
+    assert!(!empty.mutate_node_trait(10, 1.0));
+}
+
+#[test]
+fn genome_mate_multipoint() {
+    let small = weighted_genome([1.0, 1.0, 1.0]);
+    let mut big = weighted_genome([-1.0, -1.0, -1.0]);
+    let mut cur_node_id = big.get_last_node_id().unwrap();
+    let mut cur_innov_num = big.get_last_gene_innovnum().unwrap();
+    let mut innovations = InnovationTable::new();
+    assert!(big.mutate_add_node(&mut innovations, &mut cur_node_id, &mut cur_innov_num));
+
+    // The excess genes only come from the fitter parent
+    let child = small.mate_multipoint(&big, 2.0, 1.0, 10);
+    assert_eq!(child.id, 10);
+    assert_eq!(child.nodes_n(), 4);
+    assert_eq!(child.genes().len(), 3);
+    child.verify().unwrap();
+
+    let child = small.mate_multipoint(&big, 1.0, 2.0, 11);
+    assert_eq!(child.nodes_n(), 5);
+    assert_eq!(child.genes().len(), 5);
+    assert_eq!(child.get_last_gene_innovnum(), Some(5.0));
+    assert_eq!(child.traits(), big.traits());
+    child.verify().unwrap();
+
+    // Matching genes come from either parent
+    let weights: Vec<f64> = (0..50)
+        .map(|id| small.mate_multipoint(&big, 1.0, 2.0, id).genes()[0].link.weight)
+        .collect();
+    assert!(weights.contains(&1.0));
+    assert!(weights.contains(&-1.0));
+
+    // Between equally fit parents, the smaller one wins
+    let child = small.mate_multipoint(&big, 1.0, 1.0, 12);
+    assert_eq!(child.genes().len(), 3);
+}
+
+#[test]
+fn genome_mate_avg_and_singlepoint() {
+    let mut genome1 = weighted_genome([1.0, 1.0, 1.0]);
+    let mut genome2 = weighted_genome([-1.0, 3.0, -1.0]);
+    genome1.add_trait(Trait::new(4, [0.0; 8]));
+    genome2.add_trait(Trait::new(4, [1.0; 8]));
+
+    // Matching genes are averaged and traits are blended
+    let child = genome1.mate_multipoint_avg(&genome2, 1.0, 1.0, 10);
+    let weights: Vec<f64> = child.genes().iter().map(|gene| gene.link.weight).collect();
+    assert_eq!(weights, vec![0.0, 2.0, 0.0]);
+    assert_eq!(child.traits().last().unwrap().params(), &[0.5; 8]);
+    child.verify().unwrap();
+
+    // With no disjoint genes, the cut keeps one parent's genes before it,
+    // the other's after it, and averages the one at it
+    let mut cur_node_id = genome2.get_last_node_id().unwrap();
+    let mut cur_innov_num = genome2.get_last_gene_innovnum().unwrap();
+    let mut innovations = InnovationTable::new();
+    assert!(genome2.mutate_add_node(&mut innovations, &mut cur_node_id, &mut cur_innov_num));
+    for id in 0..20 {
+        let child = genome1.mate_singlepoint(&genome2, 1.0, 2.0, id);
+        child.verify().unwrap();
+        // The excess genes of the bigger parent come after any cut
+        assert_eq!(child.get_last_gene_innovnum(), Some(5.0));
+        // The first gene comes from the smaller parent, or is averaged if the cut is on it
+        let weight = child.genes()[0].link.weight;
+        assert!(weight == 1.0 || weight == 0.0);
+    }
+}
+
+#[test]
+fn genome_mate_falls_back_to_multipoint() {
+    let mut env = Env::load_from_file("assets/test.ne", true).unwrap();
+    env.mate_multipoint_prob = 0.0;
+    env.mate_multipoint_avg_prob = 0.0;
+    env.mate_singlepoint_prob = 0.0;
+
+    let small = weighted_genome([1.0, 1.0, 1.0]);
+    let mut big = weighted_genome([-1.0, -1.0, -1.0]);
+    let mut cur_node_id = big.get_last_node_id().unwrap();
+    let mut cur_innov_num = big.get_last_gene_innovnum().unwrap();
+    let mut innovations = InnovationTable::new();
+    assert!(big.mutate_add_node(&mut innovations, &mut cur_node_id, &mut cur_innov_num));
+
+    // Single point crossover would keep the excess genes of the bigger parent,
+    // averaging would give a 0 weight to the first gene
+    for id in 0..20 {
+        let child = small.mate(&big, 2.0, 1.0, id, &env);
+        assert_eq!(child.genes().len(), 3);
+        assert_eq!(child.genes()[0].link.weight.abs(), 1.0);
+    }
+}
+
+#[test]
+fn trait_blend_and_distance() {
+    let trait1 = Trait::new(1, [0.0, 0.2, 0.4, 0.6, 0.8, 1.0, 0.0, 0.0]);
+    let trait2 = Trait::new(2, [1.0, 0.2, 0.4, 0.6, 0.8, 1.0, 0.0, 1.0]);
+
+    let blended = Trait::blend(&trait1, &trait2);
+    assert_eq!(blended.id, 1);
+    assert_eq!(blended.params(), &[0.5, 0.2, 0.4, 0.6, 0.8, 1.0, 0.0, 0.5]);
+
+    assert_eq!(trait1.distance(&trait1), 0.0);
+    assert_eq!(trait1.distance(&trait2), 0.25);
+    assert_eq!(blended.distance(&trait2), 0.125);
+
+    // Traits with the same id are compared in the compatibility
+    let mut env = Env::new();
+    env.trait_diff_coeff = 2.0;
+    let mut genome1 = weighted_genome([1.0, 1.0, 1.0]);
+    let mut genome2 = weighted_genome([1.0, 1.0, 1.0]);
+    assert_eq!(genome1.compatibility(&genome2, &env), 0.0);
+    genome1.add_trait(Trait::new(4, [0.0; 8]));
+    genome2.add_trait(Trait::new(4, [0.5; 8]));
+    genome1.add_trait(Trait::new(5, [0.0; 8]));
+    genome2.add_trait(Trait::new(6, [0.5; 8]));
+    assert_eq!(genome1.trait_distance(&genome2), 0.5);
+    assert_eq!(genome1.compatibility(&genome2, &env), 1.0);
+}
+
+#[test]
+fn innovations_are_shared_within_a_generation() {
+    let mut innovations = InnovationTable::new();
+    let mut genome1 = xor_start_genome();
+    let mut genome2 = xor_start_genome();
+    let mut cur_node_id = genome1.get_last_node_id().unwrap();
+    let mut cur_innov_num = genome1.get_last_gene_innovnum().unwrap();
+
+    // Split the same gene in both genomes: the second split reuses the numbers
+    genome1.mutate_add_node(&mut innovations, &mut cur_node_id, &mut cur_innov_num);
+    let split = genome1.genes().iter().position(|gene| !gene.enabled()).unwrap();
+    loop {
+        let mut candidate = genome2.clone(2);
+        candidate.mutate_add_node(&mut innovations, &mut cur_node_id, &mut cur_innov_num);
+        if !candidate.genes()[split].enabled() {
+            genome2 = candidate;
+            break;
+        }
+    }
+    assert_eq!(genome2.get_last_node_id(), genome1.get_last_node_id());
+    assert_eq!(genome2.get_last_gene_innovnum(), genome1.get_last_gene_innovnum());
+
+    // The same link made twice gets the same innovation and weight
+    let mut env = Env::new();
+    env.newlink_tries = 50;
+    env.recur_only_prob = 1.0;
+    let mut genome3 = xor_start_genome();
+    let mut genome4 = xor_start_genome();
+    let mut cur_innov_num = 3.0;
+    innovations.clear();
+    assert!(genome3.mutate_add_link(&env, &mut innovations, &mut cur_innov_num));
+    assert!(genome4.mutate_add_link(&env, &mut innovations, &mut cur_innov_num));
+    assert_eq!(cur_innov_num, 4.0);
+    assert_eq!(innovations.len(), 1);
+    assert_eq!(genome3.genes()[3].get_innovation_num(), genome4.genes()[3].get_innovation_num());
+    assert_eq!(genome3.genes()[3].link.weight, genome4.genes()[3].link.weight);
+}
+
+#[test]
+fn population_epoch_replaces_generation() {
+    let mut env = Env::load_from_file("assets/test.ne", true).unwrap();
+    env.pop_size = 50;
+    env.mutate_add_node_prob = 0.2;
+    env.mutate_add_link_prob = 0.2;
+
+    let mut population = Population::new(&xor_start_genome(), env.pop_size, &env);
+
+    for generation in 1..6 {
+        run_epochs(&mut population, &env, generation..generation + 1, |organism| 1.0 + organism.genome.nodes_n() as f64);
+
+        assert_eq!(population.organisms.len(), env.pop_size);
+        assert!(population.organisms.iter().all(|organism| organism.borrow().has_species()));
+        let species_total: usize = population.species.iter()
+            .map(|species| species.borrow().organisms.len())
+            .sum();
+        assert_eq!(species_total, env.pop_size);
+        population.verify().unwrap();
+    }
+}
+
+#[test]
+fn population_keeps_champions() {
+    let mut env = Env::load_from_file("assets/test.ne", true).unwrap();
+    env.pop_size = 30;
+    env.compat_threshold = 1000.0;
+
+    let mut population = Population::new(&xor_start_genome(), env.pop_size, &env);
+    assert_eq!(population.species.len(), 1);
+
+    // The last organism has the highest id, so it is the champion
+    let champion_genes = population.organisms.last().unwrap().borrow().genome.genes().to_vec();
+    run_epochs(&mut population, &env, 1..2, |organism| 1.0 + organism.genome.id as f64);
+
+    // The species champion is copied unchanged
+    assert!(population.organisms.iter().any(|organism| organism.borrow().genome.genes() == &champion_genes[..]));
+}
+
+#[test]
+fn population_steals_babies() {
+    let mut env = Env::load_from_file("assets/test.ne", true).unwrap();
+    env.pop_size = 10;
+    env.babies_stolen = 10;
+    env.dropoff_age = 5;
+    let population = Population::new(&xor_start_genome(), env.pop_size, &env);
+
+    // (age, age of last improvement, expected offspring), best species first
+    let setup = [(1, 1, 10), (1, 1, 6), (10, 10, 4), (10, 0, 8)];
+    let sorted_species: Vec<Rc<RefCell<Species>>> = setup.iter().enumerate()
+        .map(|(id, &(age, age_of_last_improvement, expected_offspring))| {
+            let mut species = Species::new(id + 1);
+            species.age = age;
+            species.age_of_last_improvement = age_of_last_improvement;
+            species.expected_offspring = expected_offspring;
+            species.add_organism(Rc::new(RefCell::new(Organism::new(1.0, Box::new(weighted_genome([1.0; 3])), 1))));
+            Rc::new(RefCell::new(species))
+        })
+        .collect();
+
+    population.steal_babies(&sorted_species, &env);
+
+    // The two old species give 7 and 3 babies. The improving ones get 2, 2 and 1,
+    // the stagnant one gets none back, and the rest goes to the best species
+    let offspring: Vec<(usize, i32)> = sorted_species.iter()
+        .map(|species| {
+            let species = species.borrow();
+            let super_champ_offspring = species.organisms[0].borrow().get_super_champ_offspring();
+            (species.expected_offspring, super_champ_offspring)
+        })
+        .collect();
+    assert_eq!(offspring, vec![(17, 7), (8, 2), (2, 1), (1, 0)]);
+    assert_eq!(offspring.iter().map(|&(expected, _)| expected).sum::<usize>(), 28);
+}
+
+#[test]
+fn population_delta_codes_when_stagnant() {
+    let mut env = Env::load_from_file("assets/test.ne", true).unwrap();
+    env.pop_size = 40;
+    env.compat_threshold = 1.0;
+    env.babies_stolen = 0;
+    env.dropoff_age = 2;
+
+    let mut population = Population::new(&xor_start_genome(), env.pop_size, &env);
+
+    // The best fitness is reached on the first generation and never improves.
+    // Without stolen babies, only delta coding gives the population champion
+    // super champion offspring, 7 (dropoff_age + 5) generations after the last change
+    let mut delta_coded = Vec::new();
+    for generation in 1..17 {
+        run_epochs(&mut population, &env, generation..generation + 1, |_| 1.0);
+
+        assert_eq!(population.organisms.len(), env.pop_size);
+        if population.organisms.iter().any(|organism| organism.borrow().is_pop_champ_child()) {
+            delta_coded.push(generation);
+        }
+    }
+    assert_eq!(delta_coded, vec![8, 15]);
+
+    // The two best species share the offspring, the others get none
+    let sorted_species: Vec<Rc<RefCell<Species>>> = (0..3)
+        .map(|id| {
+            let mut species = Species::new(id + 1);
+            species.expected_offspring = 10;
+            species.add_organism(Rc::new(RefCell::new(Organism::new(1.0, Box::new(weighted_genome([1.0; 3])), 1))));
+            Rc::new(RefCell::new(species))
+        })
+        .collect();
+    population.delta_code(&sorted_species, 41);
+
+    let offspring: Vec<(usize, i32)> = sorted_species.iter()
+        .map(|species| {
+            let species = species.borrow();
+            let super_champ_offspring = species.organisms[0].borrow().get_super_champ_offspring();
+            (species.expected_offspring, super_champ_offspring)
+        })
+        .collect();
+    assert_eq!(offspring, vec![(20, 20), (21, 21), (0, 0)]);
+}
+
+#[test]
+fn population_speciation_persists() {
+    let mut env = Env::load_from_file("assets/test.ne", true).unwrap();
+    env.pop_size = 40;
+    env.compat_threshold = 1.0;
+
+    let mut population = Population::new(&xor_start_genome(), env.pop_size, &env);
+    let mut max_id = 0;
+
+    for generation in 1..8 {
+        let before: Vec<(usize, usize)> = population.species.iter()
+            .map(|species| (species.borrow().get_id(), species.borrow().age))
+            .collect();
+        max_id = before.iter().map(|&(id, _)| id).fold(max_id, usize::max);
+
+        run_epochs(&mut population, &env, generation..generation + 1, |_| 1.0 + rand::random::<f64>());
+
+        for species in &population.species {
+            let species = species.borrow();
+            assert!(!species.organisms.is_empty());
+            assert!(species.representative().is_some());
+            match before.iter().find(|&&(id, _)| id == species.get_id()) {
+                // Surviving species keep their id and get older
+                Some(&(_, age)) => assert_eq!(species.age, age + 1),
+                // New species are numbered after all the previous ones
+                None => {
+                    assert!(species.get_id() > max_id);
+                    assert_eq!(species.age, 0);
+                }
+            }
+        }
+    }
+}
+
+#[test]
+fn population_frees_retired_species() {
+    let mut env = Env::load_from_file("assets/test.ne", true).unwrap();
+    env.pop_size = 40;
+    env.compat_threshold = 1.0;
+
+    let mut population = Population::new(&xor_start_genome(), env.pop_size, &env);
+    let mut all_species: Vec<(usize, Weak<RefCell<Species>>)> = Vec::new();
+
+    for generation in 1..8 {
+        for species in &population.species {
+            all_species.push((species.borrow().get_id(), Rc::downgrade(species)));
+        }
+        run_epochs(&mut population, &env, generation..generation + 1, |_| 1.0 + rand::random::<f64>());
+    }
+
+    // Nothing keeps the species dropped from the population alive
+    let alive: Vec<usize> = population.species.iter().map(|species| species.borrow().get_id()).collect();
+    for (id, species) in &all_species {
+        assert_eq!(species.upgrade().is_some(), alive.contains(id), "species {}", id);
+    }
+}
+
+#[test]
+fn population_adjusts_compat_threshold() {
+    let mut env = Env::load_from_file("assets/test.ne", true).unwrap();
+    env.pop_size = 40;
+    env.compat_threshold = 100.0;
+    env.target_species = 5;
+    env.compat_threshold_step = 10.0;
+    env.compat_threshold_min = 0.5;
+
+    let mut population = Population::new(&xor_start_genome(), env.pop_size, &env);
+    assert_eq!(population.species.len(), 1);
+    assert_eq!(population.get_compat_threshold(), Some(100.0));
+
+    // Too few species: the threshold goes down until it reaches the floor
+    let mut last_threshold = population.get_compat_threshold().unwrap();
+    for generation in 1..15 {
+        run_epochs(&mut population, &env, generation..generation + 1, |_| 1.0 + rand::random::<f64>());
+
+        let threshold = population.get_compat_threshold().unwrap();
+        assert!(threshold >= env.compat_threshold_min);
+        assert!((threshold - last_threshold).abs() <= env.compat_threshold_step);
+        last_threshold = threshold;
+    }
+    assert!(population.get_compat_threshold().unwrap() < 100.0);
+    assert!(population.species.len() > 1);
+}
+
+#[test]
+fn env_reads_compat_thresh_alias() {
+    let env = Env::load_from_file("assets/p2nv.ne", false).unwrap();
+    assert_eq!(env.compat_threshold, 4.0);
+}
+
+#[test]
+fn genome_compatibility_options() {
+    let genome1 = weighted_genome([1.0, 1.0, 1.0]);
+    let mut genome2 = weighted_genome([0.0, 1.0, 3.0]);
+    genome2.add_gene(Gene::new(None, 2, 4, 1.0, true, 4.0, 0.0, true));
+    genome2.add_gene(Gene::new(None, 3, 4, 1.0, true, 5.0, 0.0, true));
+
+    let mut compatibility = Compatibility {
+        disjoint_coeff: 1.0,
+        excess_coeff: 1.0,
+        mutdiff_coeff: 0.0,
+        weight_diff_coeff: 0.0,
+        node_diff_coeff: 0.0,
+        trait_diff_coeff: 0.0,
+        normalize_cutoff: None,
+    };
+    assert_eq!(compatibility.distance(&genome1, &genome2), 2.0);
+
+    // Small genomes are not normalized
+    compatibility.normalize_cutoff = Some(20);
+    assert_eq!(compatibility.distance(&genome1, &genome2), 2.0);
+    compatibility.normalize_cutoff = Some(5);
+    assert_eq!(compatibility.distance(&genome1, &genome2), 0.4);
+
+    // Mean weight difference of the matching genes
+    compatibility.normalize_cutoff = None;
+    compatibility.excess_coeff = 0.0;
+    compatibility.weight_diff_coeff = 1.0;
+    assert_eq!(compatibility.distance(&genome1, &genome2), 1.0);
+
+    // Genomes without matching genes have no parametric difference
+    let mut disjoint = Genome::new(2);
+    disjoint.add_node(Node::new(1, None, NodeType::Sensor, NodePlace::Bias));
+    disjoint.add_node(Node::new(4, None, NodeType::Neuron, NodePlace::Output));
+    disjoint.add_gene(Gene::new(None, 1, 4, 1.0, false, 10.0, 3.0, true));
+    compatibility.mutdiff_coeff = 1.0;
+    compatibility.excess_coeff = 1.0;
+    let distance = compatibility.distance(&genome1, &disjoint);
+    assert_eq!(distance, 4.0);
+
+    // Nodes missing from one genome, or with another function
+    compatibility = Compatibility { node_diff_coeff: 0.5, ..compatibility };
+    assert_eq!(compatibility.distance(&genome1, &disjoint), 5.0);
+    let mut genome3 = weighted_genome([1.0, 1.0, 1.0]);
+    let mut cur_node_id = 4;
+    let mut cur_innov_num = 3.0;
+    genome3.mutate_add_node(&mut InnovationTable::new(), &mut cur_node_id, &mut cur_innov_num);
+    assert_eq!(compatibility.distance(&genome1, &genome3), 2.5);
+
+    // The Env measure leaves the optional terms out
+    let env = Env::load_from_file("assets/test.ne", false).unwrap();
+    assert_eq!(genome1.compatibility(&disjoint, &env), 4.0);
+}
+
+#[test]
+fn genome_alignment() {
+    let mut genome1 = weighted_genome([1.0, 1.0, 1.0]);
+    let mut genome2 = weighted_genome([2.0, 2.0, 2.0]);
+    genome1.add_gene(Gene::new(None, 2, 4, 1.0, true, 5.0, 0.0, true));
+    genome2.add_gene(Gene::new(None, 3, 4, 1.0, true, 4.0, 0.0, true));
+    genome2.add_gene(Gene::new(None, 4, 4, 1.0, true, 6.0, 0.0, true));
+    genome2.add_gene(Gene::new(None, 1, 4, 1.0, true, 7.0, 0.0, true));
+
+    let alignment = genome1.align(&genome2);
+    let innovations = |genes: &[&Gene]| genes.iter().map(|gene| gene.get_innovation_num()).collect::<Vec<f64>>();
+
+    assert_eq!(alignment.matching_n(), 3);
+    assert!(alignment.matching.iter().all(|(gene1, gene2)| gene1.get_innovation_num() == gene2.get_innovation_num()));
+    assert_eq!(alignment.matching[0].0.link.weight, 1.0);
+    assert_eq!(alignment.matching[0].1.link.weight, 2.0);
+    assert_eq!(innovations(&alignment.disjoint1), vec![5.0]);
+    assert_eq!(innovations(&alignment.disjoint2), vec![4.0]);
+    assert!(alignment.excess1.is_empty());
+    assert_eq!(innovations(&alignment.excess2), vec![6.0, 7.0]);
+    assert_eq!(alignment.disjoint_n(), 2);
+    assert_eq!(alignment.excess_n(), 2);
+
+    // All the genes, in innovation order
+    let order: Vec<f64> = alignment.pairs.iter()
+        .map(|pair| pair.0.or(pair.1).unwrap().get_innovation_num())
+        .collect();
+    assert_eq!(order, vec![1.0, 2.0, 3.0, 4.0, 5.0, 6.0, 7.0]);
+
+    // Swapping the genomes swaps the sides
+    let swapped = genome2.align(&genome1);
+    assert_eq!(innovations(&swapped.disjoint1), vec![4.0]);
+    assert_eq!(innovations(&swapped.excess1), vec![6.0, 7.0]);
+}
+
+#[test]
+fn population_kmedoids_speciation() {
+    let mut env = Env::load_from_file("assets/test.ne", true).unwrap();
+    env.pop_size = 40;
+
+    let speciation = Box::new(KMedoidsSpeciation::new(4));
+    let mut population = Population::with_speciation(&xor_start_genome(), env.pop_size, &env, speciation);
+    assert_eq!(population.species.len(), 4);
+    assert_eq!(population.get_compat_threshold(), None);
+
+    for generation in 1..5 {
+        let before: Vec<usize> = population.species.iter().map(|species| species.borrow().get_id()).collect();
+
+        run_epochs(&mut population, &env, generation..generation + 1, |_| 1.0 + rand::random::<f64>());
+
+        // Always the same number of species, each still holding its medoid
+        assert_eq!(population.species.len(), 4);
+        let placed: usize = population.species.iter().map(|species| species.borrow().organisms.len()).sum();
+        assert_eq!(placed, population.organisms.len());
+        for species in &population.species {
+            assert!(species.borrow().representative().is_some());
+        }
+        // The species are continued, as long as there is room for them
+        let kept = population.species.iter().filter(|species| before.contains(&species.borrow().get_id())).count();
+        assert_eq!(kept, before.len().min(4));
+    }
+}
+
+#[test]
+fn population_kmedoids_speciation_needs_a_species() {
+    let mut env = Env::load_from_file("assets/test.ne", true).unwrap();
+    env.pop_size = 20;
+
+    let speciation = Box::new(KMedoidsSpeciation::new(0));
+    let mut population = Population::with_speciation(&xor_start_genome(), env.pop_size, &env, speciation);
+    assert_eq!(population.species.len(), 1);
+    assert!(population.organisms.iter().all(|organism| organism.borrow().has_species()));
+
+    run_epochs(&mut population, &env, 1..3, |_| 1.0 + rand::random::<f64>());
+    assert_eq!(population.species.len(), 1);
+    assert_eq!(population.species[0].borrow().organisms.len(), env.pop_size);
+}
+
+#[test]
+fn population_behavioral_speciation() {
+    let mut env = Env::load_from_file("assets/test.ne", true).unwrap();
+    env.pop_size = 30;
+
+    // The weight of the first link stands in for the behavior of an organism
+    let described = Rc::new(Cell::new(0));
+    let counter = described.clone();
+    let speciation = Box::new(BehavioralSpeciation::new(0.5, move |organism: &mut Organism| {
+        counter.set(counter.get() + 1);
+        vec![organism.genome.genes()[0].link.weight]
+    }));
+    let mut population = Population::with_speciation(&xor_start_genome(), env.pop_size, &env, speciation);
+
+    // Each organism is described once
+    assert_eq!(described.get(), env.pop_size);
+    assert!(population.species.len() > 1);
+
+    // The members behave close to the representative genome of their species,
+    // so the species are compared with the behavior of that same genome
+    for generation in 1..5 {
+        for species in &population.species {
+            let species = species.borrow();
+            let representative = species.representative().unwrap().genes()[0].link.weight;
+            for organism in &species.organisms {
+                let weight = organism.borrow().genome.genes()[0].link.weight;
+                assert!(BehavioralSpeciation::behavioral_distance(&[weight], &[representative]) < 0.5);
+            }
+        }
+
+        let species_n = population.species.len();
+        described.set(0);
+        run_epochs(&mut population, &env, generation..generation + 1, |_| 1.0 + rand::random::<f64>());
+        // Besides the new organisms, only the representatives of the species are described
+        assert!(described.get() <= env.pop_size + species_n);
+    }
+}